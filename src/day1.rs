@@ -1,25 +1,239 @@
-const START_POS: i32 = 50;
-const DIAL_COUNT: i32 = 100;
+use std::collections::HashSet;
+
+const START_POS: i64 = 50;
+const DIAL_COUNT: i64 = 100;
 
 //=====================================================================
 // Input
 //=====================================================================
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn from_char(c: char, mode: ParseMode) -> Option<Self> {
+        let c = match mode {
+            ParseMode::Strict => c,
+            ParseMode::Lenient => c.to_ascii_uppercase(),
+        };
+
+        match c {
+            'L' => Some(Direction::Left),
+            'R' => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct Input {
-    direction: char,
-    distance: i32,
+    direction: Direction,
+    distance: i64,
+}
+
+/// How forgiving the parser is about the shape of a rotation line.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ParseMode {
+    /// Only `R50` and `L50`.
+    #[default]
+    Strict,
+    /// Also accepts lowercase directions and whitespace padding, e.g. ` r 50 `.
+    Lenient,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParseErrorKind {
+    EmptyLine,
+    InvalidDirection,
+    MissingDistance,
+    InvalidDistance,
+    InvalidWheel,
+}
+
+/// Parse failure with a 1-based line and column pointing at `text`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match self.kind {
+            ParseErrorKind::EmptyLine => "empty line",
+            ParseErrorKind::InvalidDirection => "invalid direction",
+            ParseErrorKind::MissingDistance => "missing distance",
+            ParseErrorKind::InvalidDistance => "invalid distance",
+            ParseErrorKind::InvalidWheel => "invalid wheel",
+        };
+        write!(
+            f,
+            "{} at line {}, column {}: {:?}",
+            what, self.line, self.column, self.text
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn try_line_to_input(line: &str, line_number: usize, mode: ParseMode) -> Result<Input, ParseError> {
+    let error = |kind, rest: &str, text: &str| ParseError {
+        kind,
+        line: line_number,
+        column: line[..line.len() - rest.len()].chars().count() + 1,
+        text: text.to_string(),
+    };
+
+    let rest = match mode {
+        ParseMode::Strict => line,
+        ParseMode::Lenient => line.trim_start(),
+    };
+
+    let Some(c) = rest.chars().next() else {
+        return Err(error(ParseErrorKind::EmptyLine, rest, rest));
+    };
+
+    let Some(direction) = Direction::from_char(c, mode) else {
+        return Err(error(
+            ParseErrorKind::InvalidDirection,
+            rest,
+            &c.to_string(),
+        ));
+    };
+
+    let rest = &rest[c.len_utf8()..];
+    let (rest, text) = match mode {
+        ParseMode::Strict => (rest, rest),
+        ParseMode::Lenient => (rest.trim_start(), rest.trim()),
+    };
+
+    if text.is_empty() {
+        return Err(error(ParseErrorKind::MissingDistance, rest, text));
+    }
+
+    let distance = text
+        .bytes()
+        .all(|b| b.is_ascii_digit())
+        .then(|| text.parse().ok())
+        .flatten()
+        .ok_or_else(|| error(ParseErrorKind::InvalidDistance, rest, text))?;
+
+    Ok(Input {
+        direction,
+        distance,
+    })
 }
 
-fn line_to_input(line: &str) -> Input {
-    Input {
-        direction: line.chars().next().unwrap(),
-        distance: line[1..].parse().unwrap(),
+/// Parses one rotation per line, stopping at the first malformed line.
+pub fn try_parse_input(input: &str, mode: ParseMode) -> Result<Vec<Input>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| try_line_to_input(line, i + 1, mode))
+        .collect()
+}
+
+/// A rotation of one wheel of a multi-wheel [`Lock`].
+#[derive(Debug, Eq, PartialEq)]
+pub struct WheelInput {
+    wheel: usize,
+    input: Input,
+}
+
+/// Parses `2:R35` style lines. Lines without a wheel prefix turn wheel 0.
+fn try_line_to_wheel_input(
+    line: &str,
+    line_number: usize,
+    mode: ParseMode,
+) -> Result<WheelInput, ParseError> {
+    let Some((prefix, rest)) = line.split_once(':') else {
+        return Ok(WheelInput {
+            wheel: 0,
+            input: try_line_to_input(line, line_number, mode)?,
+        });
+    };
+
+    let wheel_text = match mode {
+        ParseMode::Strict => prefix,
+        ParseMode::Lenient => prefix.trim(),
+    };
+
+    let wheel = wheel_text
+        .bytes()
+        .all(|b| b.is_ascii_digit())
+        .then(|| wheel_text.parse().ok())
+        .flatten()
+        .ok_or_else(|| ParseError {
+            kind: ParseErrorKind::InvalidWheel,
+            line: line_number,
+            column: prefix.len() - prefix.trim_start().len() + 1,
+            text: wheel_text.to_string(),
+        })?;
+
+    // Errors in the rotation are reported relative to the whole line
+    let input = try_line_to_input(rest, line_number, mode).map_err(|mut e| {
+        e.column += prefix.chars().count() + 1;
+        e
+    })?;
+
+    Ok(WheelInput { wheel, input })
+}
+
+pub fn try_parse_wheel_input(input: &str, mode: ParseMode) -> Result<Vec<WheelInput>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| try_line_to_wheel_input(line, i + 1, mode))
+        .collect()
+}
+
+/// A rotation from a damaged log, where `R?` and `L?` lost their distance.
+#[derive(Debug, Eq, PartialEq)]
+pub struct RecordedRotation {
+    direction: Direction,
+    distance: Option<i64>,
+}
+
+fn try_line_to_recorded_rotation(
+    line: &str,
+    line_number: usize,
+    mode: ParseMode,
+) -> Result<RecordedRotation, ParseError> {
+    match try_line_to_input(line, line_number, mode) {
+        Ok(input) => Ok(RecordedRotation {
+            direction: input.direction,
+            distance: Some(input.distance),
+        }),
+        Err(e) if e.kind == ParseErrorKind::InvalidDistance && e.text == "?" => {
+            // The direction parsed fine, or the error would be about it
+            let c = line.trim_start().chars().next().unwrap();
+            Ok(RecordedRotation {
+                direction: Direction::from_char(c, mode).unwrap(),
+                distance: None,
+            })
+        }
+        Err(e) => Err(e),
     }
 }
 
+pub fn try_parse_recorded_input(
+    input: &str,
+    mode: ParseMode,
+) -> Result<Vec<RecordedRotation>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| try_line_to_recorded_rotation(line, i + 1, mode))
+        .collect()
+}
+
 fn parse_input(input: &str) -> Vec<Input> {
-    input.lines().map(line_to_input).collect()
+    try_parse_input(input, ParseMode::Strict).unwrap_or_else(|e| panic!("{}", e))
 }
 
 #[aoc_generator(day1)]
@@ -31,85 +245,755 @@ pub fn input_generator(input: &str) -> Vec<Input> {
 // Helpers
 //=====================================================================
 
-fn input_to_distance(input: &Input) -> i32 {
-    match input {
-        Input {
-            direction: 'R',
-            distance,
-        } => *distance,
-        Input {
-            direction: 'L',
-            distance,
-        } => -distance,
-        Input { .. } => {
-            panic!("Invalid direction")
+fn input_to_distance(input: &Input) -> i64 {
+    match input.direction {
+        Direction::Right => input.distance,
+        Direction::Left => -input.distance,
+    }
+}
+
+/// Position on a dial with `modulus` marks, always kept in `0..modulus`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct DialPosition {
+    value: i64,
+    modulus: i64,
+}
+
+impl DialPosition {
+    fn new(value: i64, modulus: i64) -> Self {
+        DialPosition {
+            value: value.rem_euclid(modulus),
+            modulus,
+        }
+    }
+
+    fn value(&self) -> i64 {
+        self.value
+    }
+
+    fn is_zero(&self) -> bool {
+        self.value == 0
+    }
+
+    /// Number of times a rotation by `d` crosses the boundary between the
+    /// last position and zero, in either direction.
+    fn wraps(&self, d: i64) -> u64 {
+        let steps = d.unsigned_abs();
+        let modulus = self.modulus as u64;
+        let value = self.value as u64;
+
+        if d >= 0 {
+            (value + steps % modulus) / modulus + steps / modulus
+        } else if steps > value {
+            1 + (steps - value - 1) / modulus
+        } else {
+            0
         }
     }
 }
 
+impl std::ops::Add<i64> for DialPosition {
+    type Output = DialPosition;
+
+    fn add(self, d: i64) -> DialPosition {
+        // Reduce first so huge distances cannot overflow the sum
+        DialPosition::new(self.value + d % self.modulus, self.modulus)
+    }
+}
+
+impl std::ops::AddAssign<i64> for DialPosition {
+    fn add_assign(&mut self, d: i64) {
+        *self = *self + d;
+    }
+}
+
+#[derive(Clone, Copy)]
 struct DialState {
-    current: i32,
-    zero_hit_count: i32,
+    current: DialPosition,
+    zero_hit_count: i64,
 }
 
 impl DialState {
-    fn new() -> Self {
+    fn with_start(start: DialPosition) -> Self {
         DialState {
-            current: START_POS,
+            current: start,
             zero_hit_count: 0,
         }
     }
 
-    fn advance_part1(&mut self, d: i32) -> i32 {
-        self.current = (self.current + d) % DIAL_COUNT;
-        if self.current == 0 {
+    fn advance_part1(&mut self, d: i64) -> i64 {
+        self.current += d;
+        if self.current.is_zero() {
             self.zero_hit_count += 1;
         }
         self.zero_hit_count
     }
 
-    fn advance_part2(&mut self, d: i32) -> i32 {
-        // This is not efficient, but it works
-        let (delta, steps) = if d > 0 { (1, d) } else { (-1, -d) };
-        for _ in 0..steps {
-            self.advance_part1(delta);
+    fn advance_part2(&mut self, d: i64) -> i64 {
+        let start = self.current.value();
+        let steps = d.unsigned_abs();
+        let dial_count = self.current.modulus as u64;
+
+        // Distance from the start to the first zero in the direction of
+        // travel. Starting on zero means a full turn is needed to hit it again.
+        let first_zero = match (d > 0, start) {
+            (_, 0) => dial_count,
+            (true, start) => dial_count - start as u64,
+            (false, start) => start as u64,
+        };
+
+        if steps >= first_zero {
+            self.zero_hit_count += (1 + (steps - first_zero) / dial_count) as i64;
         }
+
+        self.current += d;
         self.zero_hit_count
     }
 }
 
+/// Which puzzle part's counting rule a [`DialState`] advances with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Rule {
+    /// Count rotations that end on zero.
+    Part1,
+    /// Count every click that points at zero.
+    Part2,
+}
+
+/// What happened to the dial during a single rotation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RotationEvent {
+    pub index: usize,
+    pub distance: i64,
+    pub start: i64,
+    pub end: i64,
+    pub wraps: u64,
+    pub zero_hits: i64,
+}
+
+impl RotationEvent {
+    const CSV_HEADER: &str = "index,distance,start,end,wraps,zero_hits";
+
+    fn to_csv(self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.index, self.distance, self.start, self.end, self.wraps, self.zero_hits
+        )
+    }
+
+    fn to_json(self) -> String {
+        format!(
+            "{{\"index\":{},\"distance\":{},\"start\":{},\"end\":{},\"wraps\":{},\"zero_hits\":{}}}",
+            self.index, self.distance, self.start, self.end, self.wraps, self.zero_hits
+        )
+    }
+}
+
+impl DialState {
+    fn advance(&mut self, rule: Rule, d: i64) -> i64 {
+        match rule {
+            Rule::Part1 => self.advance_part1(d),
+            Rule::Part2 => self.advance_part2(d),
+        }
+    }
+
+    fn advance_traced(&mut self, rule: Rule, index: usize, d: i64) -> RotationEvent {
+        let start = self.current;
+        let hits_before = self.zero_hit_count;
+
+        self.advance(rule, d);
+
+        RotationEvent {
+            index,
+            distance: d,
+            start: start.value(),
+            end: self.current.value(),
+            wraps: start.wraps(d),
+            zero_hits: self.zero_hit_count - hits_before,
+        }
+    }
+}
+
+/// Landing and passing counts for one watched mark on a [`Dial`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MarkCount {
+    pub mark: i64,
+    /// Rotations that ended on the mark.
+    pub landed: i64,
+    /// Clicks that pointed at the mark in the middle of a rotation.
+    pub passed: i64,
+}
+
+impl MarkCount {
+    /// Every click that pointed at the mark, whether the rotation ended there
+    /// or not.
+    pub fn hits(&self) -> i64 {
+        self.landed + self.passed
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DialReport {
+    pub position: i64,
+    pub marks: Vec<MarkCount>,
+}
+
+impl DialReport {
+    pub fn mark(&self, mark: i64) -> Option<&MarkCount> {
+        self.marks.iter().find(|m| m.mark == mark)
+    }
+}
+
+/// Dial model with a configurable size, start position and set of watched
+/// marks.
+///
+/// ```ignore
+/// let report = Dial::new(100).start_at(50).watch(0).watch(25).run(distances);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dial {
+    size: i64,
+    start: i64,
+    marks: Vec<i64>,
+}
+
+impl Dial {
+    pub fn new(size: i64) -> Self {
+        assert!(size > 0, "Dial size must be positive");
+        Dial {
+            size,
+            start: 0,
+            marks: Vec::new(),
+        }
+    }
+
+    /// The dial from the puzzle: 100 positions, starting at 50, watching 0.
+    pub fn puzzle() -> Self {
+        Dial::new(DIAL_COUNT).start_at(START_POS).watch(0)
+    }
+
+    pub fn start_at(mut self, start: i64) -> Self {
+        self.start = start.rem_euclid(self.size);
+        self
+    }
+
+    pub fn watch(mut self, mark: i64) -> Self {
+        let mark = mark.rem_euclid(self.size);
+        if !self.marks.contains(&mark) {
+            self.marks.push(mark);
+        }
+        self
+    }
+
+    pub fn run<I: IntoIterator<Item = i64>>(&self, distances: I) -> DialReport {
+        // Hitting a mark is the same as hitting zero on a dial that has been
+        // turned back by the mark, so every mark gets its own pair of
+        // zero-tracking states, one per counting rule.
+        let mut states: Vec<(DialState, DialState)> = self
+            .marks
+            .iter()
+            .map(|mark| {
+                let start = DialPosition::new(self.start - mark, self.size);
+                (DialState::with_start(start), DialState::with_start(start))
+            })
+            .collect();
+
+        let mut position = DialPosition::new(self.start, self.size);
+
+        for d in distances {
+            position += d;
+            for (landing, passing) in states.iter_mut() {
+                landing.advance_part1(d);
+                passing.advance_part2(d);
+            }
+        }
+
+        DialReport {
+            position: position.value(),
+            marks: self
+                .marks
+                .iter()
+                .zip(states)
+                .map(|(mark, (landing, passing))| MarkCount {
+                    mark: *mark,
+                    landed: landing.zero_hit_count,
+                    passed: passing.zero_hit_count - landing.zero_hit_count,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Zero hits and final position of one wheel of a [`Lock`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WheelReport {
+    pub position: i64,
+    /// Rotations, including carries, that ended on zero.
+    pub zero_landed: i64,
+    /// Clicks, including carries, that pointed at zero.
+    pub zero_hits: i64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LockReport {
+    pub wheels: Vec<WheelReport>,
+}
+
+impl LockReport {
+    pub fn combination(&self) -> Vec<i64> {
+        self.wheels.iter().map(|w| w.position).collect()
+    }
+}
+
+/// Rotation of a wheel the lock does not have, on a 1-based line of the log.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UnknownWheel {
+    pub wheel: usize,
+    pub line: usize,
+    /// Number of wheels of the lock.
+    pub wheels: usize,
+}
+
+impl std::fmt::Display for UnknownWheel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "wheel {} at line {} is out of range for a {} wheel lock",
+            self.wheel, self.line, self.wheels
+        )
+    }
+}
+
+impl std::error::Error for UnknownWheel {}
+
+/// Combination lock whose wheels carry into each other like an odometer.
+///
+/// Every time a wheel wraps past zero the next wheel is turned one click in
+/// the same direction. Carries out of the last wheel are lost.
+#[derive(Default)]
+pub struct Lock {
+    wheels: Vec<(DialState, DialState)>,
+}
+
+impl Lock {
+    pub fn new() -> Self {
+        Lock { wheels: Vec::new() }
+    }
+
+    /// The single wheel lock from the puzzle.
+    pub fn puzzle() -> Self {
+        Lock::new().wheel(DIAL_COUNT, START_POS)
+    }
+
+    /// Adds a wheel that is carried into by the previously added wheel.
+    pub fn wheel(mut self, size: i64, start: i64) -> Self {
+        assert!(size > 0, "Wheel size must be positive");
+        let start = DialPosition::new(start, size);
+        self.wheels
+            .push((DialState::with_start(start), DialState::with_start(start)));
+        self
+    }
+
+    /// Turns one wheel, carrying into the wheels after it.
+    ///
+    /// Panics if the lock has no such wheel.
+    pub fn rotate(&mut self, wheel: usize, d: i64) {
+        assert!(
+            wheel < self.wheels.len(),
+            "Wheel {} out of range for a {} wheel lock",
+            wheel,
+            self.wheels.len()
+        );
+
+        let mut d = d;
+        for (landing, passing) in self.wheels[wheel..].iter_mut() {
+            if d == 0 {
+                break;
+            }
+
+            let carry = landing.current.wraps(d) as i64 * d.signum();
+            landing.advance_part1(d);
+            passing.advance_part2(d);
+            d = carry;
+        }
+    }
+
+    /// Applies every rotation of a parsed log, failing before turning any
+    /// wheel if the log names a wheel the lock does not have.
+    pub fn run(mut self, input: &[WheelInput]) -> Result<LockReport, UnknownWheel> {
+        if let Some((i, w)) = input
+            .iter()
+            .enumerate()
+            .find(|(_, w)| w.wheel >= self.wheels.len())
+        {
+            return Err(UnknownWheel {
+                wheel: w.wheel,
+                line: i + 1,
+                wheels: self.wheels.len(),
+            });
+        }
+
+        for w in input {
+            self.rotate(w.wheel, input_to_distance(&w.input));
+        }
+        Ok(self.report())
+    }
+
+    pub fn report(&self) -> LockReport {
+        LockReport {
+            wheels: self
+                .wheels
+                .iter()
+                .map(|(landing, passing)| WheelReport {
+                    position: landing.current.value(),
+                    zero_landed: landing.zero_hit_count,
+                    zero_hits: passing.zero_hit_count,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What is known about the end of a damaged log.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LogConstraints {
+    pub final_position: i64,
+    pub zero_hits: i64,
+    /// Counting rule `zero_hits` was recorded with.
+    pub rule: Rule,
+    /// Unknown distances are searched in `0..=max_distance`.
+    pub max_distance: i64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NoSolution;
+
+impl std::fmt::Display for NoSolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no assignment of the unknown distances fits the log")
+    }
+}
+
+impl std::error::Error for NoSolution {}
+
+struct UnknownDistanceSearch<'a> {
+    log: &'a [RecordedRotation],
+    constraints: LogConstraints,
+    /// States `(index, position, hits)` known to lead to no solution.
+    dead: HashSet<(usize, i64, i64)>,
+    assignment: Vec<i64>,
+    solutions: Vec<Vec<i64>>,
+}
+
+impl UnknownDistanceSearch<'_> {
+    fn is_hopeless(&self, index: usize, state: &DialState) -> bool {
+        let remaining = (self.log.len() - index) as i64;
+        state.zero_hit_count > self.constraints.zero_hits
+            || (self.constraints.rule == Rule::Part1
+                && state.zero_hit_count + remaining < self.constraints.zero_hits)
+    }
+
+    fn search(&mut self, mut index: usize, mut state: DialState) -> bool {
+        // Known rotations leave nothing to choose, so walk them directly
+        while let Some(RecordedRotation {
+            direction,
+            distance: Some(distance),
+        }) = self.log.get(index)
+        {
+            if self.is_hopeless(index, &state) {
+                return false;
+            }
+            let d = input_to_distance(&Input {
+                direction: *direction,
+                distance: *distance,
+            });
+            state.advance(self.constraints.rule, d);
+            index += 1;
+        }
+
+        if self.is_hopeless(index, &state) {
+            return false;
+        }
+
+        let Some(rotation) = self.log.get(index) else {
+            let found = state.current.value() == self.constraints.final_position
+                && state.zero_hit_count == self.constraints.zero_hits;
+            if found {
+                self.solutions.push(self.assignment.clone());
+            }
+            return found;
+        };
+
+        let key = (index, state.current.value(), state.zero_hit_count);
+        if self.dead.contains(&key) {
+            return false;
+        }
+
+        let mut found = false;
+        for distance in 0..=self.constraints.max_distance {
+            let d = input_to_distance(&Input {
+                direction: rotation.direction,
+                distance,
+            });
+            let mut next = state;
+            next.advance(self.constraints.rule, d);
+
+            self.assignment.push(distance);
+            found |= self.search(index + 1, next);
+            self.assignment.pop();
+        }
+
+        if !found {
+            self.dead.insert(key);
+        }
+        found
+    }
+}
+
+/// Finds every assignment of the unknown distances in a damaged log, in order
+/// of appearance, that takes the puzzle dial to the known final position with
+/// the known number of zero hits.
+pub fn solve_unknown_distances(
+    log: &[RecordedRotation],
+    constraints: LogConstraints,
+) -> Result<Vec<Vec<i64>>, NoSolution> {
+    let mut search = UnknownDistanceSearch {
+        log,
+        constraints,
+        dead: HashSet::new(),
+        assignment: Vec::new(),
+        solutions: Vec::new(),
+    };
+
+    search.search(
+        0,
+        DialState::with_start(DialPosition::new(START_POS, DIAL_COUNT)),
+    );
+
+    if search.solutions.is_empty() {
+        Err(NoSolution)
+    } else {
+        Ok(search.solutions)
+    }
+}
+
 //=====================================================================
 // Solvers
 //=====================================================================
 
-fn solve_part1(input: &[Input]) -> i32 {
-    input
-        .iter()
-        .map(input_to_distance)
-        .scan(DialState::new(), |state, d| Some(state.advance_part1(d)))
-        .last()
-        .unwrap()
+fn solve_part1(input: &[Input]) -> i64 {
+    Dial::puzzle()
+        .run(input.iter().map(input_to_distance))
+        .marks[0]
+        .landed
 }
 
 #[aoc(day1, part1)]
-pub fn part1(input: &[Input]) -> i32 {
+pub fn part1(input: &[Input]) -> i64 {
     solve_part1(input)
 }
 
-fn solve_part2(input: &[Input]) -> i32 {
-    input
-        .iter()
-        .map(input_to_distance)
-        .scan(DialState::new(), |state, d| Some(state.advance_part2(d)))
-        .last()
-        .unwrap()
+fn solve_part2(input: &[Input]) -> i64 {
+    Dial::puzzle()
+        .run(input.iter().map(input_to_distance))
+        .marks[0]
+        .hits()
 }
 
 #[aoc(day1, part2)]
-pub fn part2(input: &[Input]) -> i32 {
+pub fn part2(input: &[Input]) -> i64 {
     solve_part2(input)
 }
 
+#[derive(Debug)]
+pub enum StreamError {
+    Io(std::io::Error),
+    Parse(ParseError),
+}
+
+impl std::fmt::Display for StreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamError::Io(e) => write!(f, "read failed: {}", e),
+            StreamError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {}
+
+/// Solves both parts in one pass over a rotation log without collecting the
+/// rotations, reusing a single line buffer so memory use stays constant.
+pub fn solve_stream<R: std::io::BufRead>(
+    mut reader: R,
+    mode: ParseMode,
+) -> Result<(i64, i64), StreamError> {
+    let start = DialPosition::new(START_POS, DIAL_COUNT);
+    let mut part1_state = DialState::with_start(start);
+    let mut part2_state = DialState::with_start(start);
+
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(StreamError::Io)? == 0 {
+            break;
+        }
+        line_number += 1;
+
+        let trimmed = line.trim_end_matches(['\n', '\r']);
+        let input = try_line_to_input(trimmed, line_number, mode).map_err(StreamError::Parse)?;
+
+        let d = input_to_distance(&input);
+        part1_state.advance_part1(d);
+        part2_state.advance_part2(d);
+    }
+
+    Ok((part1_state.zero_hit_count, part2_state.zero_hit_count))
+}
+
+/// One event per rotation as the puzzle dial advances under `rule`.
+pub fn trace(input: &[Input], rule: Rule) -> impl Iterator<Item = RotationEvent> + '_ {
+    input.iter().map(input_to_distance).enumerate().scan(
+        DialState::with_start(DialPosition::new(START_POS, DIAL_COUNT)),
+        move |state, (i, d)| Some(state.advance_traced(rule, i, d)),
+    )
+}
+
+/// Zero hits under both rules for every possible start position of a dial.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StartSweep {
+    /// Part 1 hit count, indexed by start position.
+    pub part1: Vec<i64>,
+    /// Part 2 hit count, indexed by start position.
+    pub part2: Vec<i64>,
+}
+
+impl StartSweep {
+    pub fn hits(&self, rule: Rule) -> &[i64] {
+        match rule {
+            Rule::Part1 => &self.part1,
+            Rule::Part2 => &self.part2,
+        }
+    }
+
+    /// Lowest start position with the most hits, as `(start, hits)`.
+    pub fn max(&self, rule: Rule) -> (i64, i64) {
+        let (start, hits) = self
+            .hits(rule)
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, hits)| **hits)
+            .unwrap();
+        (start as i64, *hits)
+    }
+
+    /// Lowest start position with the fewest hits, as `(start, hits)`.
+    pub fn min(&self, rule: Rule) -> (i64, i64) {
+        let (start, hits) = self
+            .hits(rule)
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, hits)| **hits)
+            .unwrap();
+        (start as i64, *hits)
+    }
+}
+
+/// Adds one to `len` consecutive entries of a difference array, wrapping
+/// around the end of the dial.
+fn add_circular(diff: &mut [i64], start: usize, len: usize) {
+    let n = diff.len() - 1;
+    let end = start + len;
+
+    diff[start] += 1;
+    if end <= n {
+        diff[end] -= 1;
+    } else {
+        diff[n] -= 1;
+        diff[0] += 1;
+        diff[end - n] -= 1;
+    }
+}
+
+/// Computes the hit counts of every start position in one pass over the
+/// rotations, in O(rotations + size) time and O(size) memory.
+pub fn sweep_start_positions<I: IntoIterator<Item = i64>>(distances: I, size: i64) -> StartSweep {
+    assert!(size > 0, "Dial size must be positive");
+    let n = size as usize;
+
+    // Every start position sees the same sequence of offsets from where it
+    // started, so part 1 only needs to know how often each offset is landed on.
+    let mut landings = vec![0i64; n];
+    let mut offset = DialPosition::new(0, size);
+
+    // Full turns hit zero once for every start position. The remaining partial
+    // turn hits zero for a contiguous arc of start positions.
+    let mut full_turns: i64 = 0;
+    let mut partial_hits = vec![0i64; n + 1];
+
+    for d in distances {
+        let steps = d.unsigned_abs();
+        let remainder = (steps % n as u64) as i64;
+        full_turns += (steps / n as u64) as i64;
+
+        if remainder > 0 {
+            // Positions relative to the dial that reach zero within the
+            // partial turn; see DialState::advance_part2.
+            let first = if d > 0 { size - remainder } else { 1 };
+            let start = (first - offset.value()).rem_euclid(size);
+            add_circular(&mut partial_hits, start as usize, remainder as usize);
+        }
+
+        offset += d;
+        landings[offset.value() as usize] += 1;
+    }
+
+    let part1 = (0..size)
+        .map(|start| landings[(-start).rem_euclid(size) as usize])
+        .collect();
+
+    let part2 = partial_hits[..n]
+        .iter()
+        .scan(0, |acc, diff| {
+            *acc += diff;
+            Some(full_turns + *acc)
+        })
+        .collect();
+
+    StartSweep { part1, part2 }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TraceFormat {
+    /// Comma separated values with a header row.
+    Csv,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+pub fn write_trace<W: std::io::Write, I: IntoIterator<Item = RotationEvent>>(
+    mut writer: W,
+    events: I,
+    format: TraceFormat,
+) -> std::io::Result<()> {
+    if format == TraceFormat::Csv {
+        writeln!(writer, "{}", RotationEvent::CSV_HEADER)?;
+    }
+
+    for event in events {
+        let line = match format {
+            TraceFormat::Csv => event.to_csv(),
+            TraceFormat::JsonLines => event.to_json(),
+        };
+        writeln!(writer, "{}", line)?;
+    }
+
+    Ok(())
+}
+
 //=====================================================================
 // Tests
 //=====================================================================
@@ -118,20 +1002,22 @@ pub fn part2(input: &[Input]) -> i32 {
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "L68\nL30\nR48\nL5\nR60\nL55\nL1\nL99\nR14\nL82";
+
     #[test]
     fn test_line_to_input() {
         assert_eq!(
-            line_to_input("R50"),
+            try_line_to_input("R50", 1, ParseMode::Strict).unwrap(),
             Input {
-                direction: 'R',
+                direction: Direction::Right,
                 distance: 50,
             }
         );
 
         assert_eq!(
-            line_to_input("L50"),
+            try_line_to_input("L50", 1, ParseMode::Strict).unwrap(),
             Input {
-                direction: 'L',
+                direction: Direction::Left,
                 distance: 50,
             }
         );
@@ -140,7 +1026,7 @@ mod tests {
     #[test]
     fn test_part2_one_wraparound() {
         let input = vec![Input {
-            direction: 'R',
+            direction: Direction::Right,
             distance: 60,
         }];
         assert_eq!(solve_part2(&input), 1);
@@ -149,7 +1035,7 @@ mod tests {
     #[test]
     fn test_part2_multiple_wraparounds() {
         let input = vec![Input {
-            direction: 'R',
+            direction: Direction::Right,
             distance: 1000,
         }];
         assert_eq!(solve_part2(&input), 10);
@@ -158,7 +1044,7 @@ mod tests {
     #[test]
     fn test_part2_land_on_zero_right() {
         let input = vec![Input {
-            direction: 'R',
+            direction: Direction::Right,
             distance: 50,
         }];
         assert_eq!(solve_part2(&input), 1);
@@ -167,9 +1053,469 @@ mod tests {
     #[test]
     fn test_part2_land_on_zero_left() {
         let input = vec![Input {
-            direction: 'L',
+            direction: Direction::Left,
             distance: 50,
         }];
         assert_eq!(solve_part2(&input), 1);
     }
+
+    #[test]
+    fn test_part2_start_on_zero() {
+        let input = vec![
+            Input {
+                direction: Direction::Left,
+                distance: 50,
+            },
+            Input {
+                direction: Direction::Left,
+                distance: 99,
+            },
+            Input {
+                direction: Direction::Left,
+                distance: 1,
+            },
+        ];
+        assert_eq!(solve_part2(&input), 2);
+    }
+
+    #[test]
+    fn test_part2_huge_distance() {
+        let input = vec![
+            Input {
+                direction: Direction::Right,
+                distance: 1_000_000_000,
+            },
+            Input {
+                direction: Direction::Left,
+                distance: i64::MAX,
+            },
+        ];
+        assert_eq!(solve_part2(&input), 10_000_000 + i64::MAX / 100);
+    }
+
+    #[test]
+    fn test_advance_part2_matches_single_steps() {
+        for start in 0..DIAL_COUNT {
+            for d in -250..=250 {
+                let mut fast = DialState {
+                    current: DialPosition::new(start, DIAL_COUNT),
+                    zero_hit_count: 0,
+                };
+                let mut slow = DialState {
+                    current: DialPosition::new(start, DIAL_COUNT),
+                    zero_hit_count: 0,
+                };
+                fast.advance_part2(d);
+                for _ in 0..d.abs() {
+                    slow.advance_part1(d.signum());
+                }
+                assert_eq!(fast.zero_hit_count, slow.zero_hit_count);
+                assert_eq!(fast.current, slow.current);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dial_position_normalizes() {
+        assert_eq!(DialPosition::new(-1, DIAL_COUNT).value(), 99);
+        assert_eq!(DialPosition::new(250, DIAL_COUNT).value(), 50);
+        assert_eq!((DialPosition::new(0, DIAL_COUNT) + -101).value(), 99);
+        assert_eq!((DialPosition::new(10, DIAL_COUNT) + i64::MIN).value(), 2);
+    }
+
+    #[test]
+    fn test_part1_long_left_run() {
+        // Each L30 from 50 cycles through ten positions, hitting zero once
+        // per cycle without ever leaving 0..DIAL_COUNT.
+        let input: Vec<Input> = (0..50)
+            .map(|_| Input {
+                direction: Direction::Left,
+                distance: 30,
+            })
+            .collect();
+        assert_eq!(solve_part1(&input), 5);
+
+        let mut state = DialState::with_start(DialPosition::new(START_POS, DIAL_COUNT));
+        for _ in 0..1000 {
+            state.advance_part1(-7);
+            assert!((0..DIAL_COUNT).contains(&state.current.value()));
+        }
+    }
+
+    #[test]
+    fn test_part1_left_past_zero() {
+        let mut state = DialState::with_start(DialPosition::new(START_POS, DIAL_COUNT));
+        state.advance_part1(-160);
+        assert_eq!(state.current.value(), 90);
+        assert_eq!(state.advance_part1(-90), 1);
+        assert_eq!(state.current.value(), 0);
+    }
+
+    #[test]
+    fn test_dial_multiple_marks() {
+        // 10 position dial starting at 3: R9 ends on 2 passing 4..=9 and 0,
+        // L5 ends on 7 passing 1, 0, 9, 8.
+        let report = Dial::new(10)
+            .start_at(3)
+            .watch(0)
+            .watch(2)
+            .watch(7)
+            .run([9, -5]);
+
+        assert_eq!(report.position, 7);
+        assert_eq!(
+            report.mark(0),
+            Some(&MarkCount {
+                mark: 0,
+                landed: 0,
+                passed: 2,
+            })
+        );
+        assert_eq!(
+            report.mark(2),
+            Some(&MarkCount {
+                mark: 2,
+                landed: 1,
+                passed: 0,
+            })
+        );
+        assert_eq!(
+            report.mark(7),
+            Some(&MarkCount {
+                mark: 7,
+                landed: 1,
+                passed: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_dial_puzzle_preset() {
+        let report = Dial::puzzle().run(parse_input(EXAMPLE).iter().map(input_to_distance));
+        assert_eq!(report.position, 32);
+        assert_eq!(report.mark(0).unwrap().landed, 3);
+        assert_eq!(report.mark(0).unwrap().hits(), 6);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = try_parse_input("R50\n\nL5", ParseMode::Strict).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::EmptyLine);
+        assert_eq!((error.line, error.column), (2, 1));
+
+        let error = try_parse_input("R50\nX5", ParseMode::Strict).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidDirection);
+        assert_eq!((error.line, error.column), (2, 1));
+        assert_eq!(error.text, "X");
+
+        let error = try_parse_input("R50\nL5\nR", ParseMode::Strict).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::MissingDistance);
+        assert_eq!((error.line, error.column), (3, 2));
+
+        let error = try_parse_input("R5x0", ParseMode::Strict).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidDistance);
+        assert_eq!((error.line, error.column), (1, 2));
+        assert_eq!(error.text, "5x0");
+        assert_eq!(
+            error.to_string(),
+            "invalid distance at line 1, column 2: \"5x0\""
+        );
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        assert!(try_parse_input("r 50", ParseMode::Strict).is_err());
+        assert!(try_parse_input("R50 ", ParseMode::Strict).is_err());
+        assert_eq!(
+            try_parse_input("r 50\n  L7  ", ParseMode::Lenient),
+            Ok(vec![
+                Input {
+                    direction: Direction::Right,
+                    distance: 50,
+                },
+                Input {
+                    direction: Direction::Left,
+                    distance: 7,
+                },
+            ])
+        );
+
+        let error = try_parse_input("  r 5 0 ", ParseMode::Lenient).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidDistance);
+        assert_eq!((error.line, error.column), (1, 5));
+        assert_eq!(error.text, "5 0");
+
+        let error = try_parse_input("R1\n   ", ParseMode::Lenient).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::EmptyLine);
+        assert_eq!((error.line, error.column), (2, 4));
+    }
+
+    #[test]
+    fn test_solve_stream_matches_in_memory() {
+        let log = format!("{}\n", EXAMPLE);
+        let input = parse_input(&log);
+
+        assert_eq!(
+            solve_stream(log.as_bytes(), ParseMode::Strict).unwrap(),
+            (solve_part1(&input), solve_part2(&input))
+        );
+
+        let crlf = log.replace('\n', "\r\n");
+        assert_eq!(
+            solve_stream(crlf.as_bytes(), ParseMode::Strict).unwrap(),
+            (3, 6)
+        );
+    }
+
+    #[test]
+    fn test_solve_stream_parse_error() {
+        match solve_stream("R1\nL2\nQ3\n".as_bytes(), ParseMode::Strict) {
+            Err(StreamError::Parse(e)) => {
+                assert_eq!(e.kind, ParseErrorKind::InvalidDirection);
+                assert_eq!(e.line, 3);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_wraps() {
+        let p = DialPosition::new(50, DIAL_COUNT);
+        assert_eq!(p.wraps(49), 0);
+        assert_eq!(p.wraps(50), 1);
+        assert_eq!(p.wraps(250), 3);
+        assert_eq!(p.wraps(-50), 0);
+        assert_eq!(p.wraps(-51), 1);
+        assert_eq!(p.wraps(-251), 3);
+        assert_eq!(DialPosition::new(0, DIAL_COUNT).wraps(-100), 1);
+    }
+
+    #[test]
+    fn test_trace_rules() {
+        let input = parse_input("L68\nL30\nR48\nR200");
+
+        let part1: Vec<RotationEvent> = trace(&input, Rule::Part1).collect();
+        let part2: Vec<RotationEvent> = trace(&input, Rule::Part2).collect();
+
+        assert_eq!(
+            part2[0],
+            RotationEvent {
+                index: 0,
+                distance: -68,
+                start: 50,
+                end: 82,
+                wraps: 1,
+                zero_hits: 1,
+            }
+        );
+        assert_eq!(part1[0].zero_hits, 0);
+        assert_eq!(
+            part1.iter().map(|e| e.zero_hits).sum::<i64>(),
+            solve_part1(&input)
+        );
+        assert_eq!(
+            part2.iter().map(|e| e.zero_hits).sum::<i64>(),
+            solve_part2(&input)
+        );
+    }
+
+    #[test]
+    fn test_write_trace() {
+        let input = parse_input("L68\nL30");
+
+        let mut csv = Vec::new();
+        write_trace(&mut csv, trace(&input, Rule::Part2), TraceFormat::Csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "index,distance,start,end,wraps,zero_hits\n0,-68,50,82,1,1\n1,-30,82,52,0,0\n"
+        );
+
+        let mut json = Vec::new();
+        write_trace(
+            &mut json,
+            trace(&input, Rule::Part1),
+            TraceFormat::JsonLines,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap().lines().next().unwrap(),
+            r#"{"index":0,"distance":-68,"start":50,"end":82,"wraps":1,"zero_hits":0}"#
+        );
+    }
+
+    #[test]
+    fn test_sweep_start_positions_matches_dial() {
+        let distances = [7, -13, 25, 3, -40, 11, -1, 0, 26, -9];
+
+        for size in [1, 5, 12, 13] {
+            let sweep = sweep_start_positions(distances, size);
+
+            for start in 0..size {
+                let report = Dial::new(size).start_at(start).watch(0).run(distances);
+                let mark = report.mark(0).unwrap();
+                assert_eq!(sweep.part1[start as usize], mark.landed);
+                assert_eq!(sweep.part2[start as usize], mark.hits());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sweep_start_positions_puzzle() {
+        let input = parse_input(EXAMPLE);
+        let sweep = sweep_start_positions(input.iter().map(input_to_distance), DIAL_COUNT);
+
+        assert_eq!(sweep.part1[START_POS as usize], solve_part1(&input));
+        assert_eq!(sweep.part2[START_POS as usize], solve_part2(&input));
+        assert_eq!(sweep.max(Rule::Part1), (50, 3));
+        assert_eq!(sweep.max(Rule::Part2), (37, 6));
+        assert_eq!(sweep.min(Rule::Part2), (0, 4));
+    }
+
+    #[test]
+    fn test_parse_wheel_input() {
+        assert_eq!(
+            try_parse_wheel_input("2:R35\nL5", ParseMode::Strict),
+            Ok(vec![
+                WheelInput {
+                    wheel: 2,
+                    input: Input {
+                        direction: Direction::Right,
+                        distance: 35,
+                    },
+                },
+                WheelInput {
+                    wheel: 0,
+                    input: Input {
+                        direction: Direction::Left,
+                        distance: 5,
+                    },
+                },
+            ])
+        );
+
+        let error = try_parse_wheel_input("x:R35", ParseMode::Strict).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidWheel);
+        assert_eq!(error.column, 1);
+
+        let error = try_parse_wheel_input("12:Rx", ParseMode::Strict).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InvalidDistance);
+        assert_eq!(error.column, 5);
+    }
+
+    #[test]
+    fn test_lock_carries() {
+        let input = try_parse_wheel_input("0:R25\n0:L6\n1:L1\n1:L1", ParseMode::Strict).unwrap();
+        let mut lock = Lock::new().wheel(10, 0).wheel(10, 0);
+
+        lock.rotate(input[0].wheel, input_to_distance(&input[0].input));
+        assert_eq!(lock.report().combination(), vec![5, 2]);
+
+        lock.rotate(input[1].wheel, input_to_distance(&input[1].input));
+        assert_eq!(lock.report().combination(), vec![9, 1]);
+
+        let report = Lock::new().wheel(10, 0).wheel(10, 0).run(&input).unwrap();
+        assert_eq!(report.combination(), vec![9, 9]);
+        assert_eq!(
+            report.wheels[0],
+            WheelReport {
+                position: 9,
+                zero_landed: 0,
+                zero_hits: 3,
+            }
+        );
+        assert_eq!(
+            report.wheels[1],
+            WheelReport {
+                position: 9,
+                zero_landed: 1,
+                zero_hits: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_lock_single_wheel_matches_puzzle() {
+        let input = parse_input(EXAMPLE);
+        let report = Lock::puzzle()
+            .run(&try_parse_wheel_input(EXAMPLE, ParseMode::Strict).unwrap())
+            .unwrap();
+
+        assert_eq!(report.wheels[0].zero_landed, solve_part1(&input));
+        assert_eq!(report.wheels[0].zero_hits, solve_part2(&input));
+    }
+
+    #[test]
+    fn test_lock_unknown_wheel() {
+        let input = try_parse_wheel_input("0:R5\n3:R5", ParseMode::Strict).unwrap();
+
+        assert_eq!(
+            Lock::puzzle().run(&input),
+            Err(UnknownWheel {
+                wheel: 3,
+                line: 2,
+                wheels: 1,
+            })
+        );
+        assert!(Lock::new().wheel(10, 0).run(&input[..1]).is_ok());
+    }
+
+    #[test]
+    fn test_parse_recorded_input() {
+        assert_eq!(
+            try_parse_recorded_input("R5\nl ? ", ParseMode::Lenient),
+            Ok(vec![
+                RecordedRotation {
+                    direction: Direction::Right,
+                    distance: Some(5),
+                },
+                RecordedRotation {
+                    direction: Direction::Left,
+                    distance: None,
+                },
+            ])
+        );
+        assert!(try_parse_recorded_input("X?", ParseMode::Strict).is_err());
+    }
+
+    #[test]
+    fn test_solve_unknown_distances() {
+        let log = try_parse_recorded_input("L68\nL?\nR48\nL?", ParseMode::Strict).unwrap();
+
+        for rule in [Rule::Part1, Rule::Part2] {
+            for final_position in [0, 32, 99] {
+                for zero_hits in 0..5 {
+                    let constraints = LogConstraints {
+                        final_position,
+                        zero_hits,
+                        rule,
+                        max_distance: 150,
+                    };
+
+                    // Brute force every assignment for comparison
+                    let mut expected = Vec::new();
+                    for a in 0..=150 {
+                        for b in 0..=150 {
+                            let mut state =
+                                DialState::with_start(DialPosition::new(START_POS, DIAL_COUNT));
+                            for d in [-68, -a, 48, -b] {
+                                state.advance(rule, d);
+                            }
+                            if state.current.value() == final_position
+                                && state.zero_hit_count == zero_hits
+                            {
+                                expected.push(vec![a, b]);
+                            }
+                        }
+                    }
+
+                    let actual = solve_unknown_distances(&log, constraints);
+                    if expected.is_empty() {
+                        assert_eq!(actual, Err(NoSolution));
+                    } else {
+                        assert_eq!(actual, Ok(expected));
+                    }
+                }
+            }
+        }
+    }
 }
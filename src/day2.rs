@@ -1,23 +1,90 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
 //=====================================================================
 // Input
 //=====================================================================
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Range {
     start: u64,
     end: u64,
 }
 
-fn entry_to_range(line: &str) -> Range {
-    let parts: Vec<&str> = line.split('-').collect();
-    Range {
-        start: parts[0].parse().unwrap(),
-        end: parts[1].parse().unwrap(),
+impl Range {
+    /// Inclusive range of IDs. Panics if `start` is after `end`, use
+    /// [`try_parse_input`] to get an error for reversed ranges instead.
+    pub fn new(start: u64, end: u64) -> Self {
+        assert!(
+            start <= end,
+            "Range is reversed: start {} is after end {}",
+            start,
+            end
+        );
+        Range { start, end }
     }
 }
 
+/// Problem with one comma separated entry, numbered from 1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RangeError {
+    Malformed { entry: usize, text: String },
+    Reversed { entry: usize, start: u64, end: u64 },
+}
+
+impl std::fmt::Display for RangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RangeError::Malformed { entry, text } => {
+                write!(
+                    f,
+                    "range {} is not of the form start-end: {:?}",
+                    entry, text
+                )
+            }
+            RangeError::Reversed { entry, start, end } => write!(
+                f,
+                "range {} is reversed: start {} is after end {}",
+                entry, start, end
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+fn entry_to_range(entry: usize, text: &str, radix: u32) -> Result<Range, RangeError> {
+    let malformed = || RangeError::Malformed {
+        entry,
+        text: text.to_string(),
+    };
+
+    let (start, end) = text.split_once('-').ok_or_else(malformed)?;
+    let start = u64::from_str_radix(start, radix).map_err(|_| malformed())?;
+    let end = u64::from_str_radix(end, radix).map_err(|_| malformed())?;
+
+    if start > end {
+        return Err(RangeError::Reversed { entry, start, end });
+    }
+
+    Ok(Range { start, end })
+}
+
+pub fn try_parse_input(input: &str) -> Result<Vec<Range>, RangeError> {
+    try_parse_input_radix(input, 10)
+}
+
+/// Parses ranges written in any radix from 2 to 36, e.g. `1f-2a` in hex.
+pub fn try_parse_input_radix(input: &str, radix: u32) -> Result<Vec<Range>, RangeError> {
+    input
+        .split(',')
+        .enumerate()
+        .map(|(i, text)| entry_to_range(i + 1, text, radix))
+        .collect()
+}
+
 fn parse_input(input: &str) -> Vec<Range> {
-    input.split(',').map(entry_to_range).collect()
+    try_parse_input(input).unwrap_or_else(|e| panic!("{}", e))
 }
 
 #[aoc_generator(day2)]
@@ -29,69 +96,584 @@ pub fn input_generator(input: &str) -> Vec<Range> {
 // Helpers
 //=====================================================================
 
-fn is_invalid_part1(n: &u64) -> bool {
-    let n_as_string = n.to_string();
-    let digit_count = n_as_string.len();
+fn assert_radix(radix: u32) {
+    assert!(
+        (2..=36).contains(&radix),
+        "Radix must be between 2 and 36, got {}",
+        radix
+    );
+}
+
+/// Digits of an ID, most significant first, kept on the stack.
+struct Digits {
+    buffer: [u8; u64::BITS as usize],
+    len: usize,
+}
+
+impl Digits {
+    fn new(n: u64, radix: u32) -> Self {
+        let mut digits = Digits {
+            buffer: [0; u64::BITS as usize],
+            len: 0,
+        };
+
+        let mut n = n;
+        loop {
+            digits.len += 1;
+            digits.buffer[u64::BITS as usize - digits.len] = (n % radix as u64) as u8;
+            n /= radix as u64;
+            if n == 0 {
+                break;
+            }
+        }
+
+        digits
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.buffer[u64::BITS as usize - self.len..]
+    }
+
+    /// Whether the digits are a `block_length` long block repeated.
+    fn is_block_repeated(&self, block_length: usize) -> bool {
+        // Shifting a repeated block by its length gives the same digits
+        let digits = self.as_slice();
+        self.len.is_multiple_of(block_length)
+            && digits[block_length..] == digits[..self.len - block_length]
+    }
+}
+
+pub fn is_invalid_part1(n: &u64) -> bool {
+    is_invalid_part1_radix(n, 10)
+}
+
+pub fn is_invalid_part1_radix(n: &u64, radix: u32) -> bool {
+    assert_radix(radix);
+    let digits = Digits::new(*n, radix);
+
+    // Odd number of digits cannot repeat
+    digits.len.is_multiple_of(2) && digits.is_block_repeated(digits.len / 2)
+}
+
+pub fn is_invalid_part2(n: &u64) -> bool {
+    is_invalid_part2_radix(n, 10)
+}
+
+pub fn is_invalid_part2_radix(n: &u64, radix: u32) -> bool {
+    assert_radix(radix);
+    let digits = Digits::new(*n, radix);
+
+    // Consider blocks of 1, 2, 3, ... digit_count / 2
+    (1..=digits.len / 2).any(|block_length| digits.is_block_repeated(block_length))
+}
 
-    if digit_count % 2 == 1 {
-        // Odd number of digits cannot repeat
-        false
-    } else {
-        // Split at midway and compare results.
-        let (lhs, rhs) = n_as_string.split_at(digit_count / 2);
-        lhs == rhs
+/// Sorts the ranges and merges the ones that overlap or touch, so every ID is
+/// covered at most once.
+fn non_overlapping_ranges(ranges: &[Range]) -> Vec<Range> {
+    let mut ranges_sorted: Vec<Range> = ranges.to_owned();
+    ranges_sorted.sort_by_key(|r| r.start);
+
+    let mut non_overlapping: Vec<Range> = Vec::new();
+    let mut last: Option<Range> = None;
+
+    for range in ranges_sorted {
+        if let Some(last_range) = last {
+            if range.start > last_range.end.saturating_add(1) {
+                // No overlap, so push and start on new range
+                non_overlapping.push(last_range);
+                last = Some(range);
+            } else {
+                // Merge with last range
+                last = Some(Range::new(
+                    last_range.start,
+                    std::cmp::max(last_range.end, range.end),
+                ))
+            }
+        } else {
+            last = Some(range);
+        }
+    }
+
+    if let Some(last) = last {
+        non_overlapping.push(last);
     }
+
+    non_overlapping
+}
+
+/// Input indices of every pair of ranges sharing at least one ID, lowest
+/// index first.
+fn overlapping_pairs(ranges: &[Range]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_by_key(|i| ranges[*i].start);
+
+    let mut pairs = Vec::new();
+    for (n, i) in order.iter().enumerate() {
+        for j in order[n + 1..]
+            .iter()
+            .take_while(|j| ranges[**j].start <= ranges[*i].end)
+        {
+            pairs.push((std::cmp::min(*i, *j), std::cmp::max(*i, *j)));
+        }
+    }
+
+    pairs.sort();
+    pairs
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NormalizedRanges {
+    pub ranges: Vec<Range>,
+    /// Pairs of input indices that overlapped, if asked for.
+    pub overlaps: Vec<(usize, usize)>,
+}
+
+/// Merges overlapping and adjacent ranges, optionally reporting which input
+/// ranges overlapped.
+pub fn normalize_ranges(ranges: &[Range], report_overlaps: bool) -> NormalizedRanges {
+    NormalizedRanges {
+        ranges: non_overlapping_ranges(ranges),
+        overlaps: if report_overlaps {
+            overlapping_pairs(ranges)
+        } else {
+            Vec::new()
+        },
+    }
+}
+
+/// Which repeat counts of a block make an ID invalid. A block must repeat
+/// at least twice, so `Exactly(1)` matches no ID and `AtLeast(0)` or
+/// `AtLeast(1)` behave like `AtLeast(2)`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Repetition {
+    /// The whole ID is a block repeated exactly this many times.
+    Exactly(u32),
+    /// The whole ID is a block repeated this many times or more.
+    AtLeast(u32),
+}
+
+impl Repetition {
+    fn matches(&self, repeats: u32) -> bool {
+        repeats >= 2
+            && match *self {
+                Repetition::Exactly(n) => repeats == n,
+                Repetition::AtLeast(n) => repeats >= n,
+            }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct IdTotals {
+    pub count: u64,
+    pub sum: u128,
 }
 
-fn is_invalid_part2(n: &u64) -> bool {
-    let n_as_string = n.to_string();
-    let digit_count = n_as_string.len();
+impl std::ops::Add for IdTotals {
+    type Output = IdTotals;
 
-    // Consider repeats of 1, 2, 3, ... digit_count / 2
-    for repeat_count in 1..(digit_count / 2) + 1 {
-        // If repeat count doesn't divide evenly, skip
-        if digit_count % repeat_count != 0 {
-            continue;
+    fn add(self, other: IdTotals) -> IdTotals {
+        IdTotals {
+            count: self.count + other.count,
+            sum: self.sum + other.sum,
         }
+    }
+}
+
+impl std::iter::Sum for IdTotals {
+    fn sum<I: Iterator<Item = IdTotals>>(iter: I) -> IdTotals {
+        iter.fold(IdTotals::default(), |a, b| a + b)
+    }
+}
+
+/// Largest number of digits of a u64 in the radix.
+fn max_digits(radix: u32) -> u32 {
+    Digits::new(u64::MAX, radix).len as u32
+}
+
+/// An ID made of block b repeated is b * 1000..1000..1 in the radix, with the
+/// ones `period` apart and `digits` digits in total.
+fn repeat_multiplier(digits: u32, period: u32, radix: u32) -> u128 {
+    let radix = radix as u128;
+    (radix.pow(digits) - 1) / (radix.pow(period) - 1)
+}
+
+/// Totals of the `digits` long IDs in `range` made of a `period` long
+/// block repeated, whether or not the block itself repeats.
+fn periodic_totals(range: &Range, digits: u32, period: u32, radix: u32) -> IdTotals {
+    let multiplier = repeat_multiplier(digits, period, radix);
+    let radix = radix as u128;
+
+    let lowest = std::cmp::max(
+        radix.pow(period - 1),
+        (range.start as u128).div_ceil(multiplier),
+    );
+    let highest = std::cmp::min(radix.pow(period) - 1, range.end as u128 / multiplier);
+
+    if lowest > highest {
+        return IdTotals::default();
+    }
+
+    let count = highest - lowest + 1;
+    IdTotals {
+        count: count as u64,
+        sum: multiplier * (lowest + highest) * count / 2,
+    }
+}
+
+/// Block lengths that evenly divide an ID of `digits` digits, excluding the
+/// whole ID.
+fn periods(digits: u32) -> Vec<u32> {
+    (1..digits).filter(|p| digits.is_multiple_of(*p)).collect()
+}
+
+/// Whether an ID of `digits` digits with smallest period `period` is invalid.
+/// It is if any multiple of the period that is also a period of the ID gives
+/// an accepted repeat count.
+fn accepts(repetition: Repetition, digits: u32, period: u32) -> bool {
+    periods(digits)
+        .iter()
+        .any(|p| p.is_multiple_of(period) && repetition.matches(digits / p))
+}
+
+/// Totals of the `digits` long IDs in `range`, grouped by smallest period.
+fn smallest_period_totals(range: &Range, digits: u32, radix: u32) -> Vec<(u32, IdTotals)> {
+    let periods = periods(digits);
+
+    // An ID with period p also has every multiple of p that divides the digit
+    // count as a period. Count each ID once under its smallest period by
+    // subtracting the totals of the periods dividing it.
+    let mut exact: Vec<(u32, IdTotals)> = Vec::with_capacity(periods.len());
+    for period in periods {
+        let all = periodic_totals(range, digits, period, radix);
+        let smaller: IdTotals = exact
+            .iter()
+            .filter(|(p, _)| period.is_multiple_of(*p))
+            .map(|(_, t)| *t)
+            .sum();
+        exact.push((
+            period,
+            IdTotals {
+                count: all.count - smaller.count,
+                sum: all.sum - smaller.sum,
+            },
+        ));
+    }
+
+    exact
+}
+
+/// Totals of the IDs in `range` that are a block of digits in the radix
+/// repeated according to `repetition`, without visiting the IDs in between.
+pub fn invalid_id_totals(range: &Range, repetition: Repetition, radix: u32) -> IdTotals {
+    assert_radix(radix);
+    (1..=max_digits(radix))
+        .flat_map(|digits| {
+            smallest_period_totals(range, digits, radix)
+                .into_iter()
+                .filter(move |(period, _)| accepts(repetition, digits, *period))
+                .map(|(_, t)| t)
+        })
+        .sum()
+}
+
+/// Number of invalid IDs in `range` for each repeat count of their smallest
+/// block.
+pub fn repeat_histogram(range: &Range, repetition: Repetition, radix: u32) -> BTreeMap<u32, u64> {
+    assert_radix(radix);
+    let mut histogram = BTreeMap::new();
+
+    for digits in 1..=max_digits(radix) {
+        for (period, totals) in smallest_period_totals(range, digits, radix) {
+            if totals.count > 0 && accepts(repetition, digits, period) {
+                *histogram.entry(digits / period).or_default() += totals.count;
+            }
+        }
+    }
+
+    histogram
+}
+
+/// An invalid ID together with its smallest repeating block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InvalidId {
+    pub id: u64,
+    pub block: u64,
+    pub block_length: u32,
+    pub repeats: u32,
+}
+
+/// Lazily yields the invalid IDs of a range in increasing order.
+///
+/// Only the IDs that are a repeated block are generated, one stream per
+/// block length the repetition accepts, merged through a heap.
+pub struct InvalidIds {
+    end: u64,
+    repetition: Repetition,
+    radix: u32,
+    digits: u32,
+    /// Block lengths of the streams of the current digit count.
+    periods: Vec<u32>,
+    /// Next `(id, block length, block)` of each block length stream.
+    heap: BinaryHeap<Reverse<(u64, u32, u64)>>,
+}
+
+impl InvalidIds {
+    fn new(range: &Range, repetition: Repetition, radix: u32) -> Self {
+        assert_radix(radix);
+        let mut ids = InvalidIds {
+            end: range.end,
+            repetition,
+            radix,
+            digits: 0,
+            periods: Vec::new(),
+            heap: BinaryHeap::new(),
+        };
+        ids.start_digits(range.start);
+        ids
+    }
+
+    /// Moves on to the next digit count that has room for IDs at or after
+    /// `start`.
+    fn start_digits(&mut self, start: u64) {
+        while self.heap.is_empty() && self.digits < max_digits(self.radix) {
+            self.digits += 1;
+            self.periods = periods(self.digits)
+                .into_iter()
+                .filter(|p| self.repetition.matches(self.digits / p))
+                .collect();
+
+            for period in self.periods.clone() {
+                let multiplier = repeat_multiplier(self.digits, period, self.radix);
+                let block = std::cmp::max(
+                    (self.radix as u128).pow(period - 1),
+                    (start as u128).div_ceil(multiplier),
+                );
+                self.push(period, block);
+            }
+        }
+    }
+
+    fn push(&mut self, period: u32, block: u128) {
+        let id = block * repeat_multiplier(self.digits, period, self.radix);
+        if block < (self.radix as u128).pow(period) && id <= self.end as u128 {
+            self.heap.push(Reverse((id as u64, period, block as u64)));
+        }
+    }
+}
+
+impl Iterator for InvalidIds {
+    type Item = InvalidId;
+
+    fn next(&mut self) -> Option<InvalidId> {
+        loop {
+            let Reverse((id, period, block)) = self.heap.pop()?;
+            self.push(period, block as u128 + 1);
+
+            let digits = self.digits;
+            let smallest = periods(digits)
+                .into_iter()
+                .find(|p| (id as u128).is_multiple_of(repeat_multiplier(digits, *p, self.radix)))
+                .unwrap();
+
+            // Every stream whose block length is a multiple of the smallest
+            // period generates the ID, only the first of them reports it.
+            let first_stream = self
+                .periods
+                .iter()
+                .find(|p| p.is_multiple_of(smallest))
+                .copied();
+
+            if self.heap.is_empty() {
+                self.start_digits(0);
+            }
+
+            if first_stream == Some(period) {
+                return Some(InvalidId {
+                    id,
+                    block: block / (repeat_multiplier(period, smallest, self.radix) as u64),
+                    block_length: smallest,
+                    repeats: digits / smallest,
+                });
+            }
+        }
+    }
+}
+
+pub fn invalid_ids(range: &Range, repetition: Repetition, radix: u32) -> InvalidIds {
+    InvalidIds::new(range, repetition, radix)
+}
+
+/// Classifies IDs as valid or invalid.
+///
+/// Rules combine with [`IdRule::and`], [`IdRule::or`] and [`IdRule::not`].
+pub trait IdRule {
+    fn is_invalid(&self, id: u64) -> bool;
+
+    /// Sum of the invalid IDs in `range`. Checks every ID unless the rule
+    /// knows a faster way.
+    fn sum_in_range(&self, range: &Range) -> u128 {
+        (range.start..=range.end)
+            .filter(|id| self.is_invalid(*id))
+            .map(u128::from)
+            .sum()
+    }
+
+    fn and<R: IdRule>(self, other: R) -> And<Self, R>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    fn or<R: IdRule>(self, other: R) -> Or<Self, R>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
 
-        // Split string at repeat count, multiply up to the correct digit count and compare
-        let repeated_string = n_as_string
-            .split_at(repeat_count)
-            .0
-            .repeat(digit_count / repeat_count);
-        if repeated_string == n_as_string {
-            return true;
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+/// A [`Repetition`] of digits in a radix other than 10.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RepetitionInRadix {
+    repetition: Repetition,
+    radix: u32,
+}
+
+impl Repetition {
+    pub fn in_radix(self, radix: u32) -> RepetitionInRadix {
+        assert_radix(radix);
+        RepetitionInRadix {
+            repetition: self,
+            radix,
         }
     }
+}
+
+impl IdRule for RepetitionInRadix {
+    fn is_invalid(&self, id: u64) -> bool {
+        let digits = Digits::new(id, self.radix);
+
+        (1..=digits.len as u32)
+            .filter(|repeats| self.repetition.matches(*repeats))
+            .any(|repeats| {
+                digits.len.is_multiple_of(repeats as usize)
+                    && digits.is_block_repeated(digits.len / repeats as usize)
+            })
+    }
+
+    fn sum_in_range(&self, range: &Range) -> u128 {
+        invalid_id_totals(range, self.repetition, self.radix).sum
+    }
+}
+
+/// `Exactly(2)` is the part 1 rule and `AtLeast(2)` the part 2 rule, both on
+/// decimal digits.
+impl IdRule for Repetition {
+    fn is_invalid(&self, id: u64) -> bool {
+        self.in_radix(10).is_invalid(id)
+    }
+
+    fn sum_in_range(&self, range: &Range) -> u128 {
+        self.in_radix(10).sum_in_range(range)
+    }
+}
+
+/// IDs that read the same forwards and backwards.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Palindrome;
+
+impl IdRule for Palindrome {
+    fn is_invalid(&self, id: u64) -> bool {
+        let digits = Digits::new(id, 10);
+        digits.as_slice().iter().eq(digits.as_slice().iter().rev())
+    }
+}
+
+/// IDs made of a block of at least this many digits repeated two or more
+/// times.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MinBlockLength(pub u32);
+
+impl IdRule for MinBlockLength {
+    fn is_invalid(&self, id: u64) -> bool {
+        let digits = Digits::new(id, 10);
+
+        (std::cmp::max(self.0 as usize, 1)..=digits.len / 2)
+            .any(|block_length| digits.is_block_repeated(block_length))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct And<A, B>(A, B);
+
+impl<A: IdRule, B: IdRule> IdRule for And<A, B> {
+    fn is_invalid(&self, id: u64) -> bool {
+        self.0.is_invalid(id) && self.1.is_invalid(id)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Or<A, B>(A, B);
+
+impl<A: IdRule, B: IdRule> IdRule for Or<A, B> {
+    fn is_invalid(&self, id: u64) -> bool {
+        self.0.is_invalid(id) || self.1.is_invalid(id)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Not<A>(A);
 
-    false
+impl<A: IdRule> IdRule for Not<A> {
+    fn is_invalid(&self, id: u64) -> bool {
+        !self.0.is_invalid(id)
+    }
+}
+
+/// Sum of the invalid IDs covered by any of the ranges, counting IDs in
+/// overlapping ranges once.
+pub fn sum_invalid(ranges: &[Range], rule: &dyn IdRule) -> u128 {
+    non_overlapping_ranges(ranges)
+        .iter()
+        .map(|range| rule.sum_in_range(range))
+        .sum()
 }
 
 //=====================================================================
 // Solvers
 //=====================================================================
 
-fn solve_part1(input: &[Range]) -> u64 {
-    input
-        .iter()
-        .flat_map(|input| (input.start..input.end + 1).filter(is_invalid_part1))
-        .sum()
+fn solve_part1(input: &[Range]) -> u128 {
+    solve_part1_radix(input, 10)
+}
+
+pub fn solve_part1_radix(input: &[Range], radix: u32) -> u128 {
+    sum_invalid(input, &Repetition::Exactly(2).in_radix(radix))
 }
 
 #[aoc(day2, part1)]
-fn part1(input: &[Range]) -> u64 {
+fn part1(input: &[Range]) -> u128 {
     solve_part1(input)
 }
 
-fn solve_part2(input: &[Range]) -> u64 {
-    input
-        .iter()
-        .flat_map(|input| (input.start..input.end + 1).filter(is_invalid_part2))
-        .sum()
+fn solve_part2(input: &[Range]) -> u128 {
+    solve_part2_radix(input, 10)
+}
+
+pub fn solve_part2_radix(input: &[Range], radix: u32) -> u128 {
+    sum_invalid(input, &Repetition::AtLeast(2).in_radix(radix))
 }
 
 #[aoc(day2, part2)]
-fn part2(input: &[Range]) -> u64 {
+fn part2(input: &[Range]) -> u128 {
     solve_part2(input)
 }
 
@@ -119,4 +701,380 @@ mod tests {
         assert!(is_invalid_part2(&123123));
         assert!(is_invalid_part2(&121212));
     }
+
+    #[test]
+    fn test_invalid_id_totals_matches_brute_force() {
+        let ranges = [
+            (0, 2000),
+            (95, 115),
+            (998, 1012),
+            (99_000, 130_000),
+            (999_000, 1_002_000),
+        ];
+
+        for (start, end) in ranges {
+            let range = Range { start, end };
+
+            let part1: Vec<u64> = (start..=end).filter(is_invalid_part1).collect();
+            assert_eq!(
+                invalid_id_totals(&range, Repetition::Exactly(2), 10),
+                IdTotals {
+                    count: part1.len() as u64,
+                    sum: part1.iter().map(|n| *n as u128).sum(),
+                }
+            );
+
+            let part2: Vec<u64> = (start..=end).filter(is_invalid_part2).collect();
+            assert_eq!(
+                invalid_id_totals(&range, Repetition::AtLeast(2), 10),
+                IdTotals {
+                    count: part2.len() as u64,
+                    sum: part2.iter().map(|n| *n as u128).sum(),
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_id_totals_exactly_three() {
+        let range = Range {
+            start: 100,
+            end: 1_000_000,
+        };
+        let expected: Vec<u64> = (100..=1_000_000)
+            .filter(|n| {
+                let s = n.to_string();
+                s.len() % 3 == 0 && s[..s.len() / 3].repeat(3) == s
+            })
+            .collect();
+
+        let totals = invalid_id_totals(&range, Repetition::Exactly(3), 10);
+        assert_eq!(totals.count, expected.len() as u64);
+        assert_eq!(totals.sum, expected.iter().map(|n| *n as u128).sum());
+    }
+
+    #[test]
+    fn test_invalid_id_totals_full_range() {
+        let range = Range {
+            start: 0,
+            end: u64::MAX,
+        };
+        assert_eq!(
+            invalid_id_totals(&range, Repetition::Exactly(2), 10),
+            IdTotals {
+                count: 1844674407,
+                sum: 12509613850169742155792778978,
+            }
+        );
+        assert!(invalid_id_totals(&range, Repetition::AtLeast(2), 10).count > 1844674407);
+    }
+
+    #[test]
+    fn test_repetition_rule_matches_part_rules() {
+        for n in 0..200_000 {
+            assert_eq!(Repetition::Exactly(2).is_invalid(n), is_invalid_part1(&n));
+            assert_eq!(Repetition::AtLeast(2).is_invalid(n), is_invalid_part2(&n));
+        }
+        assert!(Repetition::Exactly(3).is_invalid(121212));
+        assert!(!Repetition::Exactly(3).is_invalid(1212));
+
+        let range = Range::new(1, 10_000);
+        for repetition in [
+            Repetition::Exactly(1),
+            Repetition::AtLeast(0),
+            Repetition::AtLeast(1),
+        ] {
+            let expected: u128 = (1..=10_000u64)
+                .filter(|n| repetition.is_invalid(*n))
+                .map(|n| n as u128)
+                .sum();
+            assert_eq!(
+                sum_invalid(std::slice::from_ref(&range), &repetition),
+                expected
+            );
+            assert_eq!(
+                invalid_ids(&range, repetition, 10).count() as u64,
+                invalid_id_totals(&range, repetition, 10).count
+            );
+        }
+        assert!(!Repetition::Exactly(1).is_invalid(7));
+        assert!(Repetition::AtLeast(1).is_invalid(77));
+    }
+
+    #[test]
+    fn test_rule_combinators() {
+        let ranges = vec![Range {
+            start: 1,
+            end: 1500,
+        }];
+
+        assert!(Palindrome.is_invalid(12321));
+        assert!(!Palindrome.is_invalid(1232));
+        assert!(MinBlockLength(3).is_invalid(123123));
+        assert!(!MinBlockLength(3).is_invalid(121212));
+
+        // Repdigits are the only repeated IDs that are also palindromes
+        let rule = Repetition::AtLeast(2).and(Palindrome);
+        assert_eq!(
+            sum_invalid(&ranges, &rule),
+            (1..10).map(|d| d * 11 + d * 111).sum::<u128>() + 1111
+        );
+
+        let everything = Palindrome.or(Palindrome.not());
+        assert_eq!(sum_invalid(&ranges, &everything), 1500 * 1501 / 2);
+
+        assert_eq!(
+            sum_invalid(&ranges, &Repetition::AtLeast(2)),
+            sum_invalid(&ranges, &Repetition::AtLeast(2).and(Palindrome.not()))
+                + sum_invalid(&ranges, &rule)
+        );
+    }
+
+    #[test]
+    fn test_invalid_ids() {
+        let range = Range {
+            start: 95,
+            end: 130_000,
+        };
+
+        for repetition in [
+            Repetition::Exactly(2),
+            Repetition::AtLeast(2),
+            Repetition::Exactly(3),
+            Repetition::AtLeast(3),
+        ] {
+            let ids: Vec<InvalidId> = invalid_ids(&range, repetition, 10).collect();
+            let expected: Vec<u64> = (95..=130_000)
+                .filter(|n| repetition.is_invalid(*n))
+                .collect();
+
+            assert_eq!(ids.iter().map(|i| i.id).collect::<Vec<u64>>(), expected);
+            assert_eq!(
+                repeat_histogram(&range, repetition, 10)
+                    .values()
+                    .sum::<u64>(),
+                expected.len() as u64
+            );
+        }
+
+        let mut ids = invalid_ids(
+            &Range {
+                start: 1000,
+                end: 2000,
+            },
+            Repetition::AtLeast(2),
+            10,
+        );
+        assert_eq!(
+            ids.next(),
+            Some(InvalidId {
+                id: 1010,
+                block: 10,
+                block_length: 2,
+                repeats: 2,
+            })
+        );
+        assert_eq!(
+            ids.next(),
+            Some(InvalidId {
+                id: 1111,
+                block: 1,
+                block_length: 1,
+                repeats: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_repeat_histogram() {
+        let range = Range {
+            start: 1,
+            end: 1_000_000,
+        };
+
+        // Repdigits count once, under their digit count. Blocks of two
+        // repeated twice or three times and blocks of three repeated twice
+        // count only when the block is not itself a repdigit.
+        let histogram = repeat_histogram(&range, Repetition::AtLeast(2), 10);
+        assert_eq!(
+            histogram,
+            BTreeMap::from([
+                (2, 9 + (90 - 9) + (900 - 9)),
+                (3, 9 + (90 - 9)),
+                (4, 9),
+                (5, 9),
+                (6, 9)
+            ])
+        );
+
+        let histogram = repeat_histogram(&range, Repetition::Exactly(2), 10);
+        assert_eq!(histogram.values().sum::<u64>(), 9 + 90 + 900);
+        assert_eq!(histogram[&4], 9);
+    }
+
+    #[test]
+    fn test_invalid_ids_skips_unmatched_block_lengths() {
+        // No block length of a 16 digit ID repeats exactly three times
+        let range = Range {
+            start: 10u64.pow(15),
+            end: 10u64.pow(16) - 1,
+        };
+        assert_eq!(invalid_ids(&range, Repetition::Exactly(3), 10).count(), 0);
+
+        let ids: Vec<InvalidId> = invalid_ids(&range, Repetition::Exactly(4), 10)
+            .take(3)
+            .collect();
+        assert_eq!(
+            ids.iter().map(|i| i.id).collect::<Vec<u64>>(),
+            vec![
+                1000_1000_1000_1000,
+                1001_1001_1001_1001,
+                1002_1002_1002_1002
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_ids_near_u64_max() {
+        let range = Range {
+            start: u64::MAX - 1_000_000_000,
+            end: u64::MAX,
+        };
+        let ids: Vec<InvalidId> = invalid_ids(&range, Repetition::AtLeast(2), 10).collect();
+        assert_eq!(
+            ids.len() as u64,
+            invalid_id_totals(&range, Repetition::AtLeast(2), 10).count
+        );
+        assert!(ids.windows(2).all(|w| w[0].id < w[1].id));
+    }
+
+    #[test]
+    fn test_parse_input_errors() {
+        assert_eq!(
+            try_parse_input("11-22,90-10"),
+            Err(RangeError::Reversed {
+                entry: 2,
+                start: 90,
+                end: 10,
+            })
+        );
+        assert_eq!(
+            try_parse_input("11-22,1x-5").unwrap_err().to_string(),
+            "range 2 is not of the form start-end: \"1x-5\""
+        );
+    }
+
+    #[test]
+    fn test_normalize_ranges() {
+        let ranges = vec![
+            Range::new(10, 20),
+            Range::new(30, 40),
+            Range::new(15, 25),
+            Range::new(26, 29),
+            Range::new(50, u64::MAX),
+            Range::new(u64::MAX, u64::MAX),
+        ];
+
+        assert_eq!(
+            normalize_ranges(&ranges, true),
+            NormalizedRanges {
+                ranges: vec![Range::new(10, 40), Range::new(50, u64::MAX)],
+                overlaps: vec![(0, 2), (4, 5)],
+            }
+        );
+        assert!(normalize_ranges(&ranges, false).overlaps.is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_ranges_counted_once() {
+        let ranges = parse_input("95-115,100-120,11-22");
+        assert_eq!(solve_part1(&ranges), 11 + 22 + 99);
+        assert_eq!(solve_part2(&ranges), 11 + 22 + 99 + 111);
+    }
+
+    #[test]
+    fn test_is_invalid_radix() {
+        assert!(is_invalid_part1_radix(&0b1010, 2));
+        assert!(!is_invalid_part1_radix(&0b1011, 2));
+        assert!(is_invalid_part1_radix(&0xabab, 16));
+        assert!(is_invalid_part2_radix(&0xaaa, 16));
+        assert!(!is_invalid_part1_radix(&0xaaa, 16));
+        assert!(is_invalid_part2_radix(&(35 * 36 * 36 + 35 * 36 + 35), 36));
+
+        for radix in [2, 3, 7, 16, 36] {
+            for n in 0..5000 {
+                let digits = to_string_radix(n, radix);
+                let doubled = digits.len().is_multiple_of(2)
+                    && digits[..digits.len() / 2] == digits[digits.len() / 2..];
+                assert_eq!(is_invalid_part1_radix(&n, radix), doubled);
+            }
+        }
+    }
+
+    /// Digits of `n` in `radix` by repeated division, independent of
+    /// [`Digits`].
+    fn to_string_radix(mut n: u64, radix: u32) -> String {
+        let mut digits = Vec::new();
+        loop {
+            digits.push(char::from_digit((n % radix as u64) as u32, radix).unwrap());
+            n /= radix as u64;
+            if n == 0 {
+                break;
+            }
+        }
+        digits.iter().rev().collect()
+    }
+
+    #[test]
+    fn test_to_string_radix() {
+        for n in [0, 1, 5, 255, 4096, 123_456_789, u64::MAX] {
+            assert_eq!(to_string_radix(n, 2), format!("{:b}", n));
+            assert_eq!(to_string_radix(n, 10), n.to_string());
+            assert_eq!(to_string_radix(n, 16), format!("{:x}", n));
+        }
+    }
+
+    #[test]
+    fn test_invalid_id_totals_radix() {
+        for radix in [2, 3, 16, 36] {
+            let range = Range::new(5, 70_000);
+
+            for repetition in [
+                Repetition::Exactly(2),
+                Repetition::AtLeast(2),
+                Repetition::Exactly(3),
+            ] {
+                let rule = repetition.in_radix(radix);
+                let expected: Vec<u64> = (5..=70_000).filter(|n| rule.is_invalid(*n)).collect();
+
+                assert_eq!(
+                    invalid_id_totals(&range, repetition, radix),
+                    IdTotals {
+                        count: expected.len() as u64,
+                        sum: expected.iter().map(|n| *n as u128).sum(),
+                    }
+                );
+                assert_eq!(
+                    invalid_ids(&range, repetition, radix)
+                        .map(|i| i.id)
+                        .collect::<Vec<u64>>(),
+                    expected
+                );
+            }
+        }
+
+        // Every block of 32 bits doubled fits in a u64
+        let full = Range::new(0, u64::MAX);
+        assert_eq!(
+            invalid_id_totals(&full, Repetition::Exactly(2), 2).count,
+            (1..=32).map(|n| 1u64 << (n - 1)).sum::<u64>()
+        );
+    }
+
+    #[test]
+    fn test_solve_radix() {
+        let ranges = try_parse_input_radix("a0-af,fff0-ffff", 16).unwrap();
+        assert_eq!(solve_part1_radix(&ranges, 16), 0xaa + 0xffff);
+        assert_eq!(solve_part2_radix(&ranges, 16), 0xaa + 0xffff);
+    }
 }
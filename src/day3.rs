@@ -1,3 +1,6 @@
+use std::collections::HashSet;
+use std::io::IsTerminal;
+
 //=====================================================================
 // Input
 //=====================================================================
@@ -19,50 +22,353 @@ fn parse(input: &str) -> Vec<BatteryBank> {
 // Helpers
 //=====================================================================
 
-struct BatteryBank {
+pub struct BatteryBank {
     batteries: Vec<u64>,
 }
 
 impl BatteryBank {
-    fn from_string(s: &str) -> Self {
+    pub fn from_string(s: &str) -> Self {
         BatteryBank {
             batteries: s.chars().map(|c| c.to_digit(10).unwrap() as u64).collect(),
         }
     }
 }
 
-impl BatteryBank {
-    fn joltage_helper(&self, offset: usize, n: usize) -> u64 {
-        // Find the first value in the range, skipping the first offset values
-        // and the last n - 1 values. Next recursion, find the first max value
-        // from the values after the last max value and consider one more value
-        // at the end.
-
-        // Note: max_by would normally return the last max value found, but
-        // since the iterator is reversed, the first one is returned instead.
-        let (i, max) = self
-            .batteries
+/// A joltage as its digits, most significant first, so it can be any length.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Joltage {
+    digits: Vec<u64>,
+}
+
+impl Joltage {
+    pub fn digits(&self) -> &[u64] {
+        &self.digits
+    }
+
+    /// The joltage as a number, or None if it does not fit in a u128.
+    pub fn to_u128(&self) -> Option<u128> {
+        self.digits
             .iter()
-            .enumerate()
-            .skip(offset)
-            .rev()
-            .skip(n - 1)
-            .max_by(|x, y| x.1.cmp(y.1))
-            .unwrap();
-
-        if n == 1 {
-            *max
+            .try_fold(0u128, |acc, d| acc.checked_mul(10)?.checked_add(*d as u128))
+    }
+}
+
+impl std::fmt::Display for Joltage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for d in &self.digits {
+            write!(f, "{}", d)?;
+        }
+        Ok(())
+    }
+}
+
+/// Batteries switched on in a bank, in bank order, and the joltage they give.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Selection {
+    pub indices: Vec<usize>,
+    pub joltage: Joltage,
+}
+
+/// How [`BatteryBank::render`] marks the chosen batteries.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Highlight {
+    /// `98[7]6`
+    Brackets,
+    /// Bold green, for terminals.
+    Ansi,
+}
+
+impl Highlight {
+    /// ANSI colors when stdout is a terminal, brackets otherwise.
+    pub fn for_stdout() -> Self {
+        if std::io::stdout().is_terminal() {
+            Highlight::Ansi
         } else {
-            (*max * u64::pow(10, n as u32 - 1)) + self.joltage_helper(i + 1, n - 1)
+            Highlight::Brackets
+        }
+    }
+}
+
+/// Restrictions on which batteries of a bank may be switched on together.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SelectionConstraints {
+    /// Dead slots that can never be switched on.
+    pub forbidden: HashSet<usize>,
+    /// Smallest allowed difference between two chosen indices. 2 keeps
+    /// neighbours from both being on, 0 and 1 allow anything.
+    pub min_spacing: usize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SelectionError {
+    /// The constraints leave room for at most `available` batteries.
+    NotEnoughBatteries { requested: usize, available: usize },
+}
+
+impl std::fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectionError::NotEnoughBatteries {
+                requested,
+                available,
+            } => write!(
+                f,
+                "cannot switch on {} batteries, the constraints allow at most {}",
+                requested, available
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SelectionError {}
+
+/// Whether a joltage may start with a zero battery.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LeadingZeros {
+    Allowed,
+    Forbidden,
+}
+
+impl BatteryBank {
+    /// Indices of the `k` batteries giving the largest joltage, or None if
+    /// the bank has fewer than `k` batteries.
+    fn max_joltage_indices(&self, k: usize) -> Option<Vec<usize>> {
+        if k > self.batteries.len() {
+            return None;
+        }
+
+        // Keep the chosen batteries on a stack with non-increasing values. A
+        // larger battery replaces smaller ones before it as long as there are
+        // enough batteries left to still pick k. Equal values are kept, so the
+        // first of several maximums is chosen.
+        let mut stack: Vec<usize> = Vec::with_capacity(k);
+
+        for (i, battery) in self.batteries.iter().enumerate() {
+            let remaining = self.batteries.len() - i;
+            while let Some(top) = stack.last() {
+                if self.batteries[*top] < *battery && stack.len() + remaining > k {
+                    stack.pop();
+                } else {
+                    break;
+                }
+            }
+
+            if stack.len() < k {
+                stack.push(i);
+            }
+        }
+
+        Some(stack)
+    }
+
+    fn selection(&self, indices: Vec<usize>) -> Selection {
+        Selection {
+            joltage: Joltage {
+                digits: indices.iter().map(|i| self.batteries[*i]).collect(),
+            },
+            indices,
         }
     }
 
+    /// The `k` batteries giving the largest joltage, in O(n) time, or None if
+    /// the bank has fewer than `k` batteries.
+    pub fn max_joltage_selection(&self, k: usize) -> Option<Selection> {
+        self.max_joltage_indices(k)
+            .map(|indices| self.selection(indices))
+    }
+
+    /// The `k` batteries giving the largest joltage when some slots are dead
+    /// and chosen batteries must be spaced apart, in O(n * k) time.
+    pub fn max_joltage_constrained(
+        &self,
+        k: usize,
+        constraints: &SelectionConstraints,
+    ) -> Result<Selection, SelectionError> {
+        let n = self.batteries.len();
+        // Any spacing past the end of the bank allows only one battery, so
+        // clamp it to keep the table below small.
+        let spacing = constraints.min_spacing.clamp(1, n + 1);
+        let allowed = |i: usize| !constraints.forbidden.contains(&i);
+
+        // most[i] is the most batteries that can be chosen from i onwards. It
+        // never grows with i, so the earliest of several equal batteries always
+        // leaves the most room for the rest.
+        let mut most = vec![0; n + spacing];
+        for i in (0..n).rev() {
+            most[i] = if allowed(i) {
+                std::cmp::max(most[i + 1], 1 + most[i + spacing])
+            } else {
+                most[i + 1]
+            };
+        }
+
+        if most[0] < k {
+            return Err(SelectionError::NotEnoughBatteries {
+                requested: k,
+                available: most[0],
+            });
+        }
+
+        let mut indices = Vec::with_capacity(k);
+        let mut next = 0;
+        for remaining in (1..=k).rev() {
+            // Reversed so max_by_key picks the first of several maximums
+            let i = (next..n)
+                .rev()
+                .filter(|i| allowed(*i) && 1 + most[i + spacing] >= remaining)
+                .max_by_key(|i| self.batteries[*i])
+                .unwrap();
+            indices.push(i);
+            next = i + spacing;
+        }
+
+        Ok(self.selection(indices))
+    }
+
+    /// `next[i][d]` is the first index at or after `i` holding digit `d`.
+    fn next_digit_table(&self) -> Vec<[Option<usize>; 10]> {
+        let n = self.batteries.len();
+        let mut next = vec![[None; 10]; n + 1];
+        for i in (0..n).rev() {
+            next[i] = next[i + 1];
+            next[i][self.batteries[i] as usize] = Some(i);
+        }
+        next
+    }
+
+    /// The `count` largest distinct joltages from switching on `k` batteries,
+    /// largest first, each with the earliest batteries that produce it.
+    ///
+    /// Joltages are built a digit at a time, trying larger digits first and
+    /// always taking the next battery with that digit. Only digits that leave
+    /// enough batteries to finish are tried, so every branch ends in a joltage
+    /// and the search stops after `count` of them instead of looking at every
+    /// subset.
+    pub fn top_joltages(&self, k: usize, count: usize) -> Vec<Selection> {
+        let next = self.next_digit_table();
+
+        let mut found = Vec::new();
+        if k <= self.batteries.len() {
+            self.top_joltages_helper(&next, k, count, 0, &mut Vec::new(), &mut found);
+        }
+        found
+    }
+
+    fn top_joltages_helper(
+        &self,
+        next: &[[Option<usize>; 10]],
+        k: usize,
+        count: usize,
+        offset: usize,
+        indices: &mut Vec<usize>,
+        found: &mut Vec<Selection>,
+    ) {
+        if found.len() == count {
+            return;
+        }
+
+        if indices.len() == k {
+            found.push(self.selection(indices.clone()));
+            return;
+        }
+
+        let remaining = k - indices.len();
+        for digit in (0..10).rev() {
+            if found.len() == count {
+                return;
+            }
+
+            if let Some(i) = next[offset][digit]
+                && self.batteries.len() - i >= remaining
+            {
+                indices.push(i);
+                self.top_joltages_helper(next, k, count, i + 1, indices, found);
+                indices.pop();
+            }
+        }
+    }
+
+    /// Largest joltage from switching on `k` batteries, in O(n) time, or None
+    /// if the bank has fewer than `k` batteries.
+    pub fn max_joltage(&self, k: usize) -> Option<Joltage> {
+        self.max_joltage_selection(k)
+            .map(|selection| selection.joltage)
+    }
+
+    /// The bank with the batteries at `indices` highlighted.
+    pub fn render(&self, indices: &[usize], highlight: Highlight) -> String {
+        self.batteries
+            .iter()
+            .enumerate()
+            .map(|(i, battery)| {
+                if !indices.contains(&i) {
+                    battery.to_string()
+                } else {
+                    match highlight {
+                        Highlight::Brackets => format!("[{}]", battery),
+                        Highlight::Ansi => format!("\x1b[1;32m{}\x1b[0m", battery),
+                    }
+                }
+            })
+            .collect()
+    }
+
     fn joltage_part1(&self) -> u64 {
-        self.joltage_helper(0, 2)
+        self.max_joltage(2).unwrap().to_u128().unwrap() as u64
     }
 
     fn joltage_part2(&self) -> u64 {
-        self.joltage_helper(0, 12)
+        self.max_joltage(12).unwrap().to_u128().unwrap() as u64
+    }
+
+    /// Smallest joltage from switching on `k` batteries, or None if there are
+    /// too few batteries or every possible first battery is a forbidden zero.
+    pub fn min_joltage(&self, k: usize, leading_zeros: LeadingZeros) -> Option<Selection> {
+        let n = self.batteries.len();
+        let next = self.next_digit_table();
+
+        // From offset, the earliest battery of the smallest digit that still
+        // leaves enough batteries after it is always the best choice.
+        let mut indices = Vec::with_capacity(k);
+        let mut offset = 0;
+        for remaining in (1..=k).rev() {
+            let lowest = match leading_zeros {
+                LeadingZeros::Forbidden if indices.is_empty() => 1,
+                _ => 0,
+            };
+            let i = (lowest..10)
+                .filter_map(|digit| next[offset][digit])
+                .find(|i| n - i >= remaining)?;
+            indices.push(i);
+            offset = i + 1;
+        }
+
+        Some(self.selection(indices))
+    }
+
+    /// The earliest batteries producing exactly `target` as a `k` digit
+    /// joltage, counting leading zeros, or None if the bank cannot produce it.
+    pub fn find_joltage(&self, target: u128, k: usize) -> Option<Selection> {
+        let mut digits = vec![0; k];
+        let mut rest = target;
+        for digit in digits.iter_mut().rev() {
+            *digit = (rest % 10) as usize;
+            rest /= 10;
+        }
+        if rest != 0 {
+            return None;
+        }
+
+        let next = self.next_digit_table();
+        let mut indices = Vec::with_capacity(k);
+        let mut offset = 0;
+        for digit in digits {
+            let i = next[offset][digit]?;
+            indices.push(i);
+            offset = i + 1;
+        }
+
+        Some(self.selection(indices))
     }
 }
 
@@ -70,6 +376,40 @@ impl BatteryBank {
 // Solvers
 //=====================================================================
 
+/// One line per bank with the batteries of its best `k` selection highlighted.
+/// Banks with fewer than `k` batteries show `-` instead of a joltage.
+pub fn render_selections(input: &[BatteryBank], k: usize, highlight: Highlight) -> String {
+    input
+        .iter()
+        .map(|bank| match bank.max_joltage_selection(k) {
+            Some(selection) => format!(
+                "{} {}\n",
+                bank.render(&selection.indices, highlight),
+                selection.joltage
+            ),
+            None => format!("{} -\n", bank.render(&[], highlight)),
+        })
+        .collect()
+}
+
+/// Sum of the `count` largest distinct `k` battery joltages of every bank.
+pub fn sum_top_joltages(input: &[BatteryBank], k: usize, count: usize) -> u128 {
+    input
+        .iter()
+        .flat_map(|bank| bank.top_joltages(k, count))
+        .map(|selection| {
+            selection
+                .joltage
+                .to_u128()
+                .expect("Joltage does not fit in a u128")
+        })
+        .sum()
+}
+
+pub fn print_selections(input: &[BatteryBank], k: usize) {
+    print!("{}", render_selections(input, k, Highlight::for_stdout()));
+}
+
 fn solve_part1(input: &[BatteryBank]) -> u64 {
     input.iter().map(BatteryBank::joltage_part1).sum()
 }
@@ -125,4 +465,264 @@ mod tests {
             987654321111
         );
     }
+
+    /// Indices of every k battery subset of the bank, in increasing order.
+    fn brute_force_subsets(bank: &BatteryBank, k: usize) -> impl Iterator<Item = Vec<usize>> {
+        let n = bank.batteries.len();
+        (0u32..1 << n)
+            .filter(move |mask| mask.count_ones() as usize == k)
+            .map(move |mask| (0..n).filter(|i| mask & (1 << i) != 0).collect())
+    }
+
+    fn digits_at(bank: &BatteryBank, indices: &[usize]) -> Vec<u64> {
+        indices.iter().map(|i| bank.batteries[*i]).collect()
+    }
+
+    /// Every distinct k digit joltage of the bank by trying every subset,
+    /// smallest first.
+    fn brute_force_joltages(bank: &BatteryBank, k: usize) -> Vec<Vec<u64>> {
+        let mut all: Vec<Vec<u64>> = brute_force_subsets(bank, k)
+            .map(|indices| digits_at(bank, &indices))
+            .collect();
+        all.sort();
+        all.dedup();
+        all
+    }
+
+    /// Largest k digit subsequence by trying every subset.
+    fn brute_force_max_joltage(bank: &BatteryBank, k: usize) -> Vec<u64> {
+        brute_force_joltages(bank, k).pop().unwrap()
+    }
+
+    #[test]
+    fn test_max_joltage_matches_brute_force() {
+        for s in [
+            "987654321111111",
+            "818181911112111",
+            "1213",
+            "99119",
+            "5",
+            "3030303",
+        ] {
+            let bank = BatteryBank::from_string(s);
+            for k in 0..=s.len() {
+                assert_eq!(
+                    bank.max_joltage(k).unwrap().digits(),
+                    brute_force_max_joltage(&bank, k).as_slice()
+                );
+            }
+            assert_eq!(bank.max_joltage(s.len() + 1), None);
+        }
+    }
+
+    #[test]
+    fn test_max_joltage_large_k() {
+        let s = "9".repeat(50) + "1";
+        let bank = BatteryBank::from_string(&s);
+
+        let joltage = bank.max_joltage(45).unwrap();
+        assert_eq!(joltage.to_string(), "9".repeat(45));
+        assert_eq!(joltage.to_u128(), None);
+
+        assert_eq!(
+            bank.max_joltage(38).unwrap().to_u128(),
+            Some(10u128.pow(38) - 1)
+        );
+        assert_eq!(bank.max_joltage(51).unwrap().to_string(), s);
+    }
+
+    #[test]
+    fn test_max_joltage_selection() {
+        let bank = BatteryBank::from_string("818181911112111");
+        let selection = bank.max_joltage_selection(2).unwrap();
+        assert_eq!(selection.indices, vec![6, 11]);
+        assert_eq!(selection.joltage.to_u128(), Some(92));
+    }
+
+    #[test]
+    fn test_render_selections() {
+        let banks = parse_input("811111111111119\n234234234234278");
+
+        assert_eq!(
+            render_selections(&banks, 2, Highlight::Brackets),
+            "[8]1111111111111[9] 89\n2342342342342[7][8] 78\n"
+        );
+        assert_eq!(
+            render_selections(&parse_input("12\n345"), 3, Highlight::Brackets),
+            "12 -\n[3][4][5] 345\n"
+        );
+        assert_eq!(
+            banks[0].render(&[0], Highlight::Ansi),
+            "\x1b[1;32m8\x1b[0m11111111111119"
+        );
+    }
+
+    /// Largest k digit selection honouring the constraints by trying every
+    /// subset.
+    fn brute_force_constrained(
+        bank: &BatteryBank,
+        k: usize,
+        constraints: &SelectionConstraints,
+    ) -> Option<Vec<u64>> {
+        brute_force_subsets(bank, k)
+            .filter(|indices| indices.iter().all(|i| !constraints.forbidden.contains(i)))
+            .filter(|indices| {
+                indices
+                    .windows(2)
+                    .all(|w| w[1] - w[0] >= constraints.min_spacing)
+            })
+            .map(|indices| digits_at(bank, &indices))
+            .max()
+    }
+
+    #[test]
+    fn test_max_joltage_constrained_matches_brute_force() {
+        for s in ["818181911112111", "99119", "3030303", "123456789"] {
+            let bank = BatteryBank::from_string(s);
+            for forbidden in [vec![], vec![0], vec![1, 6], vec![2, 3, 4]] {
+                for min_spacing in 0..4 {
+                    let constraints = SelectionConstraints {
+                        forbidden: forbidden.iter().cloned().collect(),
+                        min_spacing,
+                    };
+                    for k in 0..=s.len() {
+                        let expected = brute_force_constrained(&bank, k, &constraints);
+                        let actual = bank.max_joltage_constrained(k, &constraints);
+                        assert_eq!(
+                            actual.as_ref().ok().map(|s| s.joltage.digits().to_vec()),
+                            expected
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_joltage_constrained_error() {
+        let bank = BatteryBank::from_string("98765");
+        let constraints = SelectionConstraints {
+            forbidden: HashSet::from([0]),
+            min_spacing: 2,
+        };
+
+        assert_eq!(
+            bank.max_joltage_constrained(2, &constraints)
+                .unwrap()
+                .indices,
+            vec![1, 3]
+        );
+        let error = bank.max_joltage_constrained(3, &constraints).unwrap_err();
+        assert_eq!(
+            error,
+            SelectionError::NotEnoughBatteries {
+                requested: 3,
+                available: 2,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "cannot switch on 3 batteries, the constraints allow at most 2"
+        );
+
+        for min_spacing in [5, 10usize.pow(10), usize::MAX] {
+            let constraints = SelectionConstraints {
+                forbidden: HashSet::new(),
+                min_spacing,
+            };
+            assert_eq!(
+                bank.max_joltage_constrained(1, &constraints)
+                    .unwrap()
+                    .indices,
+                vec![0]
+            );
+            assert!(bank.max_joltage_constrained(2, &constraints).is_err());
+        }
+    }
+
+    #[test]
+    fn test_top_joltages_matches_brute_force() {
+        for s in ["818181911112111", "99119", "3030303", "1111", "9876"] {
+            let bank = BatteryBank::from_string(s);
+
+            for k in 0..=4 {
+                // Every distinct subsequence value, largest first
+                let mut all = brute_force_joltages(&bank, k);
+                all.reverse();
+
+                for count in [0, 1, 3, 1000] {
+                    let top = bank.top_joltages(k, count);
+                    let values: Vec<Vec<u64>> =
+                        top.iter().map(|s| s.joltage.digits().to_vec()).collect();
+                    assert_eq!(values, all[..std::cmp::min(count, all.len())]);
+
+                    for selection in top {
+                        assert_eq!(
+                            digits_at(&bank, &selection.indices),
+                            selection.joltage.digits()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sum_top_joltages() {
+        let banks = parse_input("987654321111111\n811111111111119");
+
+        assert_eq!(sum_top_joltages(&banks, 2, 1), 98 + 89);
+        assert_eq!(sum_top_joltages(&banks, 2, 2), 98 + 97 + 89 + 81);
+
+        let big = BatteryBank::from_string(&"9182736455".repeat(10));
+        assert_eq!(big.top_joltages(12, 500).len(), 500);
+    }
+
+    #[test]
+    fn test_min_joltage_matches_brute_force() {
+        for s in ["818181911112111", "0030201", "99119", "000", "5"] {
+            let bank = BatteryBank::from_string(s);
+            for k in 1..=std::cmp::min(s.len() + 1, 5) {
+                let all = brute_force_joltages(&bank, k);
+
+                assert_eq!(
+                    bank.min_joltage(k, LeadingZeros::Allowed)
+                        .map(|s| s.joltage.digits().to_vec()),
+                    all.first().cloned()
+                );
+                assert_eq!(
+                    bank.min_joltage(k, LeadingZeros::Forbidden)
+                        .map(|s| s.joltage.digits().to_vec()),
+                    all.iter().find(|j| j[0] != 0).cloned()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_joltage_matches_brute_force() {
+        for s in ["818181911112111", "0030201", "99119"] {
+            let bank = BatteryBank::from_string(s);
+            for k in 1..=3 {
+                let all = brute_force_joltages(&bank, k);
+
+                for target in 0..10u128.pow(k as u32) {
+                    let expected = all
+                        .iter()
+                        .any(|j| j.iter().fold(0u128, |acc, d| acc * 10 + *d as u128) == target);
+                    let found = bank.find_joltage(target, k);
+                    assert_eq!(found.is_some(), expected);
+
+                    if let Some(selection) = found {
+                        assert_eq!(selection.joltage.to_u128(), Some(target));
+                        assert!(selection.indices.windows(2).all(|w| w[0] < w[1]));
+                    }
+                }
+            }
+        }
+
+        let bank = BatteryBank::from_string("0030201");
+        assert_eq!(bank.find_joltage(21, 3).unwrap().indices, vec![0, 4, 6]);
+        assert!(bank.find_joltage(1000, 3).is_none());
+    }
 }
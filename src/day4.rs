@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 //=====================================================================
 // Input
@@ -8,13 +8,38 @@ fn is_roll(c: &char) -> bool {
     *c == '@'
 }
 
-fn parse_input(input: &str) -> Grid {
-    let iter = input.lines().enumerate().flat_map(|(x, line)| {
-        line.chars()
-            .enumerate()
-            .filter(|(_, c)| is_roll(c))
-            .map(move |(y, _)| Point::new(x as i32, y as i32))
-    });
+/// Splits the input into layers separated by blank lines, bottom layer
+/// first. A plain 2D map is a single layer.
+fn split_layers(input: &str) -> Vec<Vec<&str>> {
+    let mut layers: Vec<Vec<&str>> = vec![Vec::new()];
+
+    for line in input.lines() {
+        if !line.trim().is_empty() {
+            layers.last_mut().unwrap().push(line);
+        } else if !layers.last().unwrap().is_empty() {
+            layers.push(Vec::new());
+        }
+    }
+
+    if layers.len() > 1 && layers.last().unwrap().is_empty() {
+        layers.pop();
+    }
+
+    layers
+}
+
+pub fn parse_input(input: &str) -> Grid {
+    let iter = split_layers(input)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(z, layer)| {
+            layer.into_iter().enumerate().flat_map(move |(x, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| is_roll(c))
+                    .map(move |(y, _)| Point::new_3d(x as i32, y as i32, z as i32))
+            })
+        });
 
     Grid {
         rolls: HashSet::from_iter(iter),
@@ -31,32 +56,166 @@ fn parse(input: &str) -> Grid {
 //=====================================================================
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
-struct Point {
-    x: i32,
-    y: i32,
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+    /// Layer of a stacked warehouse, 0 for plain 2D maps.
+    pub z: i32,
 }
 
 impl Point {
-    fn new(x: i32, y: i32) -> Self {
-        Point { x, y }
+    pub fn new(x: i32, y: i32) -> Self {
+        Point { x, y, z: 0 }
     }
 
-    fn offset(&self, x: i32, y: i32) -> Self {
+    pub fn new_3d(x: i32, y: i32, z: i32) -> Self {
+        Point { x, y, z }
+    }
+
+    fn offset(&self, x: i32, y: i32, z: i32) -> Self {
         Point {
             x: self.x + x,
             y: self.y + y,
+            z: self.z + z,
         }
     }
 }
 
+/// Every point of the cube of the given radius around `center`.
 fn kernel_around(center: &Point, radius: i32) -> impl Iterator<Item = Point> {
     (-radius..radius + 1)
         .flat_map(move |x| std::iter::repeat(x).zip(-radius..radius + 1))
-        .map(|(x, y)| center.offset(x, y))
+        .flat_map(move |(x, y)| (-radius..radius + 1).map(move |z| (x, y, z)))
+        .map(|(x, y, z)| center.offset(x, y, z))
+}
+
+/// The cells counted as neighbours of a roll, as offsets from the roll
+/// itself. The roll's own cell is never part of the neighbourhood.
+///
+/// With wrapping enabled the offsets wrap around a torus of the given size,
+/// so rolls on one edge neighbour the rolls on the opposite edge.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Neighbourhood {
+    offsets: Vec<Point>,
+    wrap: Option<(i32, i32)>,
+}
+
+impl Neighbourhood {
+    /// Every cell within `radius` steps, diagonals and the layers above and
+    /// below included.
+    pub fn moore(radius: i32) -> Self {
+        Self::custom(kernel_around(&Point::new(0, 0), radius))
+    }
+
+    /// Every cell within `radius` orthogonal steps, through layers too.
+    pub fn von_neumann(radius: i32) -> Self {
+        Self::custom(
+            kernel_around(&Point::new(0, 0), radius)
+                .filter(|p| p.x.abs() + p.y.abs() + p.z.abs() <= radius),
+        )
+    }
+
+    /// Every cell within `radius` steps on a hex grid in axial coordinates,
+    /// where the six neighbours of a cell are the four orthogonal ones and
+    /// the two on the `(1, -1)` diagonal. Hex grids are single layer.
+    pub fn hexagonal(radius: i32) -> Self {
+        Self::custom(
+            kernel_around(&Point::new(0, 0), radius)
+                .filter(|p| p.z == 0 && (p.x + p.y).abs() <= radius),
+        )
+    }
+
+    /// The given offsets, without duplicates and without the origin.
+    pub fn custom<I: IntoIterator<Item = Point>>(offsets: I) -> Self {
+        let mut unique = Vec::new();
+        for offset in offsets {
+            if offset != Point::new(0, 0) && !unique.contains(&offset) {
+                unique.push(offset);
+            }
+        }
+
+        Neighbourhood {
+            offsets: unique,
+            wrap: None,
+        }
+    }
+
+    /// Wraps the neighbourhood around a grid of `rows` by `cols` cells. Layers
+    /// never wrap.
+    ///
+    /// On a torus smaller than the neighbourhood several offsets reach the
+    /// same cell, or the roll itself, so they are reduced to the distinct
+    /// cells other than the roll.
+    pub fn wrapping(self, rows: i32, cols: i32) -> Self {
+        assert!(
+            rows > 0 && cols > 0,
+            "Torus size must be positive, got {}x{}",
+            rows,
+            cols
+        );
+
+        let offsets = self
+            .offsets
+            .iter()
+            .map(|o| Point::new_3d(o.x.rem_euclid(rows), o.y.rem_euclid(cols), o.z));
+        Neighbourhood {
+            wrap: Some((rows, cols)),
+            ..Self::custom(offsets)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    fn wrapped(&self, p: Point) -> Point {
+        match self.wrap {
+            Some((rows, cols)) => Point::new_3d(p.x.rem_euclid(rows), p.y.rem_euclid(cols), p.z),
+            None => p,
+        }
+    }
+
+    /// The neighbours of `center`.
+    pub fn around<'a>(&'a self, center: &'a Point) -> impl Iterator<Item = Point> + 'a {
+        self.offsets
+            .iter()
+            .map(move |o| self.wrapped(center.offset(o.x, o.y, o.z)))
+    }
+
+    /// The cells that have `center` as a neighbour. This differs from
+    /// `around` when the offsets are not symmetric.
+    pub fn around_reversed<'a>(&'a self, center: &'a Point) -> impl Iterator<Item = Point> + 'a {
+        self.offsets
+            .iter()
+            .map(move |o| self.wrapped(center.offset(-o.x, -o.y, -o.z)))
+    }
+}
+
+/// When a roll can be reached by a forklift: when fewer than `threshold` of
+/// its neighbours hold rolls.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Accessibility {
+    pub neighbourhood: Neighbourhood,
+    pub threshold: u32,
+}
+
+impl Accessibility {
+    /// The rule of the puzzle: fewer than four of the eight surrounding cells
+    /// hold rolls.
+    pub fn puzzle() -> Self {
+        Accessibility {
+            neighbourhood: Neighbourhood::moore(1),
+            threshold: NEIGHBOR_THRESHOLD,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
-struct Grid {
+pub struct Grid {
     rolls: HashSet<Point>,
 }
 
@@ -65,8 +224,160 @@ impl Grid {
         self.rolls.contains(p)
     }
 
-    fn neighbor_count(&self, p: &Point) -> u32 {
-        kernel_around(p, 1).filter(|p| self.has_roll_at(p)).count() as u32
+    fn neighbor_count(&self, p: &Point, neighbourhood: &Neighbourhood) -> u32 {
+        neighbourhood
+            .around(p)
+            .filter(|p| self.has_roll_at(p))
+            .count() as u32
+    }
+
+    fn is_accessible(&self, p: &Point, rule: &Accessibility) -> bool {
+        self.neighbor_count(p, &rule.neighbourhood) < rule.threshold
+    }
+
+    /// Number of rolls accessible under the given rule.
+    pub fn accessible_count(&self, rule: &Accessibility) -> usize {
+        self.rolls
+            .iter()
+            .filter(|p| self.is_accessible(p, rule))
+            .count()
+    }
+}
+
+/// Rolls packed one bit per cell, each row padded to whole 64 bit words and
+/// each layer padded to the height of the tallest one.
+///
+/// Neighbour counts for a whole word of cells are computed at once by adding
+/// shifted row masks into bit-sliced counters, which is much faster than
+/// hashing points on dense maps.
+#[derive(Clone, Debug)]
+struct BitGrid {
+    depth: usize,
+    height: usize,
+    words_per_row: usize,
+    bits: Vec<u64>,
+}
+
+/// Bit-sliced counter planes, plane i holding bit i of each cell's count.
+/// Five planes count up to 31, enough for a radius 1 kernel in 3D.
+type Counts = [u64; 5];
+
+fn add_to_counts(counts: &mut Counts, mask: u64) {
+    let mut carry = mask;
+    for plane in counts.iter_mut() {
+        let next_carry = *plane & carry;
+        *plane ^= carry;
+        carry = next_carry;
+    }
+}
+
+/// Mask of the cells whose count is at least `n`.
+fn counts_at_least(counts: &Counts, n: u32) -> u64 {
+    // No count reaches past what the planes can hold
+    if n >= 1 << counts.len() {
+        return 0;
+    }
+
+    // Compare from the most significant plane down, tracking the cells that
+    // have matched `n` so far.
+    let mut greater = 0;
+    let mut equal = !0;
+    for (i, plane) in counts.iter().enumerate().rev() {
+        if n & (1 << i) == 0 {
+            greater |= equal & plane;
+            equal &= !plane;
+        } else {
+            equal &= plane;
+        }
+    }
+    greater | equal
+}
+
+impl BitGrid {
+    fn parse(input: &str) -> Self {
+        let layers = split_layers(input);
+        let width = input.lines().map(|line| line.len()).max().unwrap_or(0);
+        let words_per_row = width.div_ceil(64);
+        let depth = layers.len();
+        let height = layers.iter().map(|layer| layer.len()).max().unwrap_or(0);
+        let mut bits = vec![0; words_per_row * height * depth];
+
+        for (z, layer) in layers.iter().enumerate() {
+            for (x, line) in layer.iter().enumerate() {
+                let row = z * height + x;
+                for (y, c) in line.chars().enumerate() {
+                    if is_roll(&c) {
+                        bits[row * words_per_row + y / 64] |= 1 << (y % 64);
+                    }
+                }
+            }
+        }
+
+        BitGrid {
+            depth,
+            height,
+            words_per_row,
+            bits,
+        }
+    }
+
+    /// A word of a row, rows numbered layer by layer.
+    fn word(&self, row: usize, word: usize) -> u64 {
+        self.bits[row * self.words_per_row + word]
+    }
+
+    /// Sum of the word and its left and right neighbours in a row, shifted
+    /// so every cell lines up with its own bit. The unshifted word is left
+    /// out on the cells' own row, so cells do not count themselves.
+    fn add_row_to_counts(&self, counts: &mut Counts, row: usize, word: usize, own_row: bool) {
+        let center = self.word(row, word);
+        let before = if word > 0 {
+            self.word(row, word - 1)
+        } else {
+            0
+        };
+        let after = if word + 1 < self.words_per_row {
+            self.word(row, word + 1)
+        } else {
+            0
+        };
+
+        add_to_counts(counts, (center << 1) | (before >> 63));
+        if !own_row {
+            add_to_counts(counts, center);
+        }
+        add_to_counts(counts, (center >> 1) | (after << 63));
+    }
+
+    /// Per word mask of the rolls that are accessible under `rule`. Only the
+    /// radius 1 Moore neighbourhood without wrapping is supported.
+    fn accessible_rolls(&self, rule: &Accessibility) -> Vec<u64> {
+        assert_eq!(
+            rule.neighbourhood,
+            Neighbourhood::moore(1),
+            "BitGrid only counts the surrounding cells"
+        );
+
+        let mut out = vec![0; self.bits.len()];
+
+        for z in 0..self.depth {
+            for x in 0..self.height {
+                let row = z * self.height + x;
+                for word in 0..self.words_per_row {
+                    let mut counts = Counts::default();
+                    for l in z.saturating_sub(1)..std::cmp::min(z + 2, self.depth) {
+                        for r in x.saturating_sub(1)..std::cmp::min(x + 2, self.height) {
+                            let own_row = l == z && r == x;
+                            self.add_row_to_counts(&mut counts, l * self.height + r, word, own_row);
+                        }
+                    }
+                    out[row * self.words_per_row + word] =
+                        self.word(row, word) & !counts_at_least(&counts, rule.threshold);
+                }
+            }
+        }
+
+        out
     }
 }
 
@@ -74,41 +385,417 @@ impl Grid {
 // Solvers
 //=====================================================================
 
-const MAX_NEIGHBOR_COUNT: u32 = 4;
+const NEIGHBOR_THRESHOLD: u32 = 4;
 
 #[aoc(day4, part1)]
 fn part1(input: &Grid) -> usize {
-    input
-        .rolls
-        .iter()
-        .map(|p| input.neighbor_count(p))
-        .filter(|c| *c < MAX_NEIGHBOR_COUNT + 1) // add one to account for the roll itself
-        .count()
+    input.accessible_count(&Accessibility::puzzle())
+}
+
+/// Outcome of one round of removing every accessible roll.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ErosionRound {
+    pub round: usize,
+    pub removed: usize,
+    pub remaining: usize,
+}
+
+impl Grid {
+    /// Removes accessible rolls round by round until none are left, returning
+    /// the rolls removed in each round.
+    ///
+    /// A roll can only become accessible when a neighbour is removed, so after
+    /// the first round only the rolls neighbouring the rolls removed in the
+    /// previous round are checked.
+    fn peel(&self, rule: &Accessibility) -> Vec<Vec<Point>> {
+        let mut current_grid = self.clone();
+        let mut candidates: HashSet<Point> = current_grid.rolls.clone();
+        let mut rounds = Vec::new();
+
+        loop {
+            let remove: Vec<Point> = candidates
+                .iter()
+                .filter(|p| current_grid.is_accessible(p, rule))
+                .cloned()
+                .collect();
+
+            if remove.is_empty() {
+                break;
+            }
+
+            for p in remove.iter() {
+                current_grid.rolls.remove(p);
+            }
+
+            candidates = remove
+                .iter()
+                .flat_map(|p| rule.neighbourhood.around_reversed(p))
+                .filter(|p| current_grid.has_roll_at(p))
+                .collect();
+
+            rounds.push(remove);
+        }
+
+        rounds
+    }
+
+    pub fn erode(&self, rule: &Accessibility) -> Vec<ErosionRound> {
+        let mut remaining = self.rolls.len();
+
+        self.peel(rule)
+            .iter()
+            .enumerate()
+            .map(|(i, removed)| {
+                remaining -= removed.len();
+                ErosionRound {
+                    round: i + 1,
+                    removed: removed.len(),
+                    remaining,
+                }
+            })
+            .collect()
+    }
+
+    pub fn layers(&self, rule: &Accessibility) -> LayerMap {
+        let mut layers: HashMap<Point, Option<usize>> =
+            self.rolls.iter().map(|p| (p.clone(), None)).collect();
+
+        for (i, removed) in self.peel(rule).into_iter().enumerate() {
+            for p in removed {
+                layers.insert(p, Some(i + 1));
+            }
+        }
+
+        LayerMap { layers }
+    }
+}
+
+/// The round each roll is removed in, or None for the rolls of the stable
+/// core that are never removed. This is the k-core decomposition of the graph
+/// of neighbouring rolls.
+#[derive(Clone, Debug)]
+pub struct LayerMap {
+    layers: HashMap<Point, Option<usize>>,
+}
+
+/// Smallest and largest corner of the box around the points.
+fn bounding_box<'a, I: Iterator<Item = &'a Point>>(points: I) -> Option<(Point, Point)> {
+    points.fold(None, |bounds, p| match bounds {
+        None => Some((p.clone(), p.clone())),
+        Some((min, max)) => Some((
+            Point::new_3d(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+            Point::new_3d(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+        )),
+    })
+}
+
+impl LayerMap {
+    /// The round the roll at `p` is removed in, `Some(None)` for a core roll
+    /// and None if there is no roll at `p`.
+    pub fn round(&self, p: &Point) -> Option<Option<usize>> {
+        self.layers.get(p).copied()
+    }
+
+    /// Every roll with its removal round, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Point, Option<usize>)> {
+        self.layers.iter().map(|(p, round)| (p, *round))
+    }
+
+    pub fn core(&self) -> impl Iterator<Item = &Point> {
+        self.layers
+            .iter()
+            .filter(|(_, round)| round.is_none())
+            .map(|(p, _)| p)
+    }
+
+    pub fn core_size(&self) -> usize {
+        self.core().count()
+    }
+
+    pub fn core_bounds(&self) -> Option<(Point, Point)> {
+        bounding_box(self.core())
+    }
+
+    /// The map with each roll showing its removal round in base 36 (rounds
+    /// past `z` show as `+`), core rolls as `@` and empty cells as `.`.
+    /// Layers are separated by blank lines, as in the input.
+    pub fn render(&self) -> String {
+        let Some((min, max)) = bounding_box(self.layers.keys()) else {
+            return String::new();
+        };
+
+        (min.z..=max.z)
+            .map(|z| {
+                (min.x..=max.x)
+                    .map(|x| {
+                        (min.y..=max.y)
+                            .map(|y| match self.layers.get(&Point::new_3d(x, y, z)) {
+                                None => '.',
+                                Some(None) => '@',
+                                Some(Some(round)) => {
+                                    char::from_digit(*round as u32, 36).unwrap_or('+')
+                                }
+                            })
+                            .collect::<String>()
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    }
+}
+
+/// One forklift edit, adding or removing the roll at a point.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Edit {
+    Add(Point),
+    Remove(Point),
+}
+
+/// Formats the edit as it appears in a log, with the layer only when it is
+/// not the ground layer.
+impl std::fmt::Display for Edit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (sign, p) = match self {
+            Edit::Add(p) => ('+', p),
+            Edit::Remove(p) => ('-', p),
+        };
+        write!(f, "{}{},{}", sign, p.x, p.y)?;
+        if p.z != 0 {
+            write!(f, ",{}", p.z)?;
+        }
+        Ok(())
+    }
+}
+
+/// Malformed edit on a 1-based line of the log.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EditError {
+    pub line: usize,
+    pub text: String,
+}
+
+impl std::fmt::Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid edit at line {}: {:?}", self.line, self.text)
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// Parses a log of `+x,y` and `-x,y` edits, skipping blank lines. A third
+/// coordinate picks the layer of a stacked warehouse.
+pub fn try_parse_edits(log: &str) -> Result<Vec<Edit>, EditError> {
+    log.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let text = line.trim();
+            let error = || EditError {
+                line: i + 1,
+                text: text.to_string(),
+            };
+
+            let (sign, rest) = text.split_at_checked(1).ok_or_else(error)?;
+            let coords = rest
+                .split(',')
+                .map(|c| c.trim().parse::<i32>().map_err(|_| error()))
+                .collect::<Result<Vec<i32>, EditError>>()?;
+            let p = match coords[..] {
+                [x, y] => Point::new(x, y),
+                [x, y, z] => Point::new_3d(x, y, z),
+                _ => return Err(error()),
+            };
+
+            match sign {
+                "+" => Ok(Edit::Add(p)),
+                "-" => Ok(Edit::Remove(p)),
+                _ => Err(error()),
+            }
+        })
+        .collect()
+}
+
+/// Grid that keeps the neighbour count of every roll and the set of
+/// accessible rolls up to date as rolls are added and removed, so each edit
+/// only touches the cells of one neighbourhood.
+#[derive(Clone, Debug)]
+pub struct LiveGrid {
+    rule: Accessibility,
+    counts: HashMap<Point, u32>,
+    accessible: HashSet<Point>,
+}
+
+impl LiveGrid {
+    pub fn new(grid: &Grid, rule: Accessibility) -> Self {
+        let counts: HashMap<Point, u32> = grid
+            .rolls
+            .iter()
+            .map(|p| (p.clone(), grid.neighbor_count(p, &rule.neighbourhood)))
+            .collect();
+        let accessible = counts
+            .iter()
+            .filter(|(_, count)| **count < rule.threshold)
+            .map(|(p, _)| p.clone())
+            .collect();
+
+        LiveGrid {
+            rule,
+            counts,
+            accessible,
+        }
+    }
+
+    pub fn roll_count(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn accessible_count(&self) -> usize {
+        self.accessible.len()
+    }
+
+    pub fn is_accessible(&self, p: &Point) -> bool {
+        self.accessible.contains(p)
+    }
+
+    /// Moves `p` in or out of the accessible set to match its count.
+    fn update_accessible(&mut self, p: &Point) {
+        match self.counts.get(p) {
+            Some(count) if *count < self.rule.threshold => {
+                self.accessible.insert(p.clone());
+            }
+            _ => {
+                self.accessible.remove(p);
+            }
+        }
+    }
+
+    /// Adds the change in neighbour count to every roll that has `center`
+    /// as a neighbour.
+    fn update_neighbours(&mut self, center: &Point, added: bool) {
+        let affected: Vec<Point> = self.rule.neighbourhood.around_reversed(center).collect();
+
+        for q in affected {
+            if let Some(count) = self.counts.get_mut(&q) {
+                if added {
+                    *count += 1;
+                } else {
+                    *count -= 1;
+                }
+                self.update_accessible(&q);
+            }
+        }
+    }
+
+    /// Adds a roll at `p`, returning false if there already was one.
+    pub fn insert(&mut self, p: Point) -> bool {
+        if self.counts.contains_key(&p) {
+            return false;
+        }
+
+        let count = self
+            .rule
+            .neighbourhood
+            .around(&p)
+            .filter(|q| self.counts.contains_key(q))
+            .count() as u32;
+        self.counts.insert(p.clone(), count);
+        self.update_accessible(&p);
+        self.update_neighbours(&p, true);
+        true
+    }
+
+    /// Removes the roll at `p`, returning false if there was none.
+    pub fn remove(&mut self, p: &Point) -> bool {
+        if self.counts.remove(p).is_none() {
+            return false;
+        }
+
+        self.accessible.remove(p);
+        self.update_neighbours(p, false);
+        true
+    }
+
+    pub fn apply(&mut self, edit: &Edit) -> bool {
+        match edit {
+            Edit::Add(p) => self.insert(p.clone()),
+            Edit::Remove(p) => self.remove(p),
+        }
+    }
+
+    /// Applies every edit in order, returning the accessible count after
+    /// each one.
+    pub fn replay(&mut self, edits: &[Edit]) -> Vec<usize> {
+        edits
+            .iter()
+            .map(|edit| {
+                self.apply(edit);
+                self.accessible_count()
+            })
+            .collect()
+    }
+}
+
+/// Replays an edit log on top of `grid` with the puzzle rule, printing the
+/// accessible count after each edit.
+pub fn print_replay(grid: &Grid, log: &str) -> Result<(), EditError> {
+    let edits = try_parse_edits(log)?;
+    let mut live = LiveGrid::new(grid, Accessibility::puzzle());
+
+    for (edit, count) in edits.iter().zip(live.replay(&edits)) {
+        println!("{}: {}", edit, count);
+    }
+
+    Ok(())
 }
 
 #[aoc(day4, part2)]
 fn part2(input: &Grid) -> usize {
+    input
+        .erode(&Accessibility::puzzle())
+        .iter()
+        .map(|r| r.removed)
+        .sum()
+}
+
+#[aoc_generator(day4, part1, Bits)]
+fn parse_bits_part1(input: &str) -> BitGrid {
+    BitGrid::parse(input)
+}
+
+#[aoc(day4, part1, Bits)]
+fn part1_bits(input: &BitGrid) -> usize {
+    input
+        .accessible_rolls(&Accessibility::puzzle())
+        .iter()
+        .map(|w| w.count_ones() as usize)
+        .sum()
+}
+
+#[aoc_generator(day4, part2, Bits)]
+fn parse_bits_part2(input: &str) -> BitGrid {
+    BitGrid::parse(input)
+}
+
+#[aoc(day4, part2, Bits)]
+fn part2_bits(input: &BitGrid) -> usize {
+    let rule = Accessibility::puzzle();
     let mut current_grid = input.clone();
     let mut total_removed: usize = 0;
 
     loop {
-        let remove: Vec<Point> = {
-            current_grid
-                .rolls
-                .iter()
-                .filter(|p| current_grid.neighbor_count(p) < MAX_NEIGHBOR_COUNT + 1)
-                .cloned()
-                .collect()
-        };
+        let remove = current_grid.accessible_rolls(&rule);
+        let removed: usize = remove.iter().map(|w| w.count_ones() as usize).sum();
 
-        if remove.is_empty() {
+        if removed == 0 {
             break;
         }
 
-        total_removed += remove.len();
+        total_removed += removed;
 
-        for p in remove {
-            current_grid.rolls.remove(&p);
+        for (word, r) in current_grid.bits.iter_mut().zip(remove) {
+            *word &= !r;
         }
     }
 
@@ -132,15 +819,296 @@ mod tests {
 
         println!("{:?}", kernel);
 
-        assert_eq!(kernel.len(), 9);
-        assert!(kernel.contains(&p.offset(-1, -1)));
-        assert!(kernel.contains(&p.offset(-1, 0)));
-        assert!(kernel.contains(&p.offset(-1, 1)));
-        assert!(kernel.contains(&p.offset(0, -1)));
-        assert!(kernel.contains(&p.offset(0, 0)));
-        assert!(kernel.contains(&p.offset(0, 1)));
-        assert!(kernel.contains(&p.offset(1, -1)));
-        assert!(kernel.contains(&p.offset(1, 0)));
-        assert!(kernel.contains(&p.offset(1, 1)));
+        assert_eq!(kernel.len(), 27);
+        for z in -1..=1 {
+            assert!(kernel.contains(&p.offset(-1, -1, z)));
+            assert!(kernel.contains(&p.offset(-1, 0, z)));
+            assert!(kernel.contains(&p.offset(-1, 1, z)));
+            assert!(kernel.contains(&p.offset(0, -1, z)));
+            assert!(kernel.contains(&p.offset(0, 0, z)));
+            assert!(kernel.contains(&p.offset(0, 1, z)));
+            assert!(kernel.contains(&p.offset(1, -1, z)));
+            assert!(kernel.contains(&p.offset(1, 0, z)));
+            assert!(kernel.contains(&p.offset(1, 1, z)));
+        }
+    }
+
+    const EXAMPLE: &str = "..@@.@@@@.
+@@@.@.@.@@
+@@@@@.@.@@
+@.@@@@..@.
+@@.@@@@.@@
+.@@@@@@@.@
+.@.@.@.@@@
+@.@@@.@@@@
+.@@@@@@@@.
+@.@.@@@.@.";
+
+    /// Deterministic pseudo random map wider than one word.
+    fn wide_map(width: usize, height: usize) -> String {
+        let mut state: u64 = 0x2545f4914f6cdd1d;
+        (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        if state.is_multiple_of(3) { '.' } else { '@' }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_counts_at_least() {
+        for count in 0..32u32 {
+            let mut counts = Counts::default();
+            for _ in 0..count {
+                add_to_counts(&mut counts, 1);
+            }
+            for n in 0..70 {
+                assert_eq!(counts_at_least(&counts, n) & 1 == 1, count >= n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bit_grid_matches_hash_set() {
+        assert_eq!(part1_bits(&BitGrid::parse(EXAMPLE)), 13);
+        assert_eq!(part2_bits(&BitGrid::parse(EXAMPLE)), 43);
+
+        for (width, height) in [(64, 5), (65, 7), (150, 40)] {
+            let map = wide_map(width, height);
+            assert_eq!(part1_bits(&BitGrid::parse(&map)), part1(&parse_input(&map)));
+            assert_eq!(part2_bits(&BitGrid::parse(&map)), part2(&parse_input(&map)));
+
+            for threshold in [0, 1, 3, 6, 27, 32, 40] {
+                let rule = Accessibility {
+                    neighbourhood: Neighbourhood::moore(1),
+                    threshold,
+                };
+                let accessible = BitGrid::parse(&map).accessible_rolls(&rule);
+                assert_eq!(
+                    accessible
+                        .iter()
+                        .map(|w| w.count_ones() as usize)
+                        .sum::<usize>(),
+                    parse_input(&map).accessible_count(&rule)
+                );
+            }
+        }
+
+        let full = Accessibility {
+            neighbourhood: Neighbourhood::moore(1),
+            threshold: 40,
+        };
+        let accessible = BitGrid::parse("@@@\n@@@\n@@@").accessible_rolls(&full);
+        assert_eq!(accessible.iter().map(|w| w.count_ones()).sum::<u32>(), 9);
+    }
+
+    #[test]
+    fn test_erode_rounds() {
+        let rounds = parse_input(EXAMPLE).erode(&Accessibility::puzzle());
+
+        assert_eq!(
+            rounds.iter().map(|r| r.removed).collect::<Vec<usize>>(),
+            vec![13, 12, 7, 5, 2, 1, 1, 1, 1]
+        );
+        assert_eq!(
+            rounds.last(),
+            Some(&ErosionRound {
+                round: 9,
+                removed: 1,
+                remaining: 71 - 43,
+            })
+        );
+    }
+
+    #[test]
+    fn test_layers() {
+        let layers = parse_input(EXAMPLE).layers(&Accessibility::puzzle());
+
+        assert_eq!(
+            layers.render(),
+            "..11.1121.
+134.2.2.32
+24578.1.33
+2.69@@..2.
+13.@@@@.21
+.24@@@@@.2
+.2.@.@.@@3
+1.4@@.@@@4
+.23@@@@@5.
+1.1.@@@.1."
+        );
+        assert_eq!(layers.core_size(), 71 - 43);
+        assert_eq!(layers.round(&Point::new(0, 2)), Some(Some(1)));
+        assert_eq!(layers.round(&Point::new(0, 7)), Some(Some(2)));
+        assert_eq!(layers.round(&Point::new(3, 3)), Some(Some(9)));
+        assert_eq!(layers.round(&Point::new(3, 4)), Some(None));
+        assert_eq!(layers.round(&Point::new(0, 0)), None);
+        assert_eq!(layers.iter().count(), 71);
+        assert_eq!(
+            layers.iter().filter(|(_, round)| *round == Some(1)).count(),
+            13
+        );
+        assert_eq!(
+            layers.core_bounds(),
+            Some((Point::new(3, 3), Point::new(9, 8)))
+        );
+
+        let empty = parse_input("...\n.@.").layers(&Accessibility::puzzle());
+        assert_eq!(empty.render(), "1");
+        assert_eq!(empty.core_size(), 0);
+        assert_eq!(empty.core_bounds(), None);
+    }
+
+    #[test]
+    fn test_neighbourhoods() {
+        assert_eq!(Neighbourhood::moore(1).len(), 26);
+        assert_eq!(Neighbourhood::moore(2).len(), 124);
+        assert_eq!(Neighbourhood::von_neumann(1).len(), 6);
+        assert_eq!(Neighbourhood::von_neumann(2).len(), 24);
+        assert_eq!(Neighbourhood::hexagonal(1).len(), 6);
+        assert_eq!(Neighbourhood::hexagonal(2).len(), 18);
+        assert_eq!(
+            Neighbourhood::custom([Point::new(0, 0), Point::new(0, 1), Point::new(0, 1)]),
+            Neighbourhood::custom([Point::new(0, 1)])
+        );
+
+        let wrapped = Neighbourhood::moore(1).wrapping(3, 4);
+        let around: Vec<Point> = wrapped.around(&Point::new(0, 0)).collect();
+        assert!(around.contains(&Point::new(2, 3)));
+        assert!(around.contains(&Point::new(1, 1)));
+        assert!(
+            around
+                .iter()
+                .all(|p| (0..3).contains(&p.x) && (0..4).contains(&p.y))
+        );
+
+        // Offsets wrapping onto the roll itself or onto each other are only
+        // counted once
+        let single = Neighbourhood::moore(1).wrapping(1, 1);
+        assert_eq!(single.len(), 2);
+        assert_eq!(
+            parse_input("@").neighbor_count(&Point::new(0, 0), &single),
+            0
+        );
+
+        let pair = Neighbourhood::moore(1).wrapping(1, 2);
+        let grid = parse_input("@@");
+        assert_eq!(grid.neighbor_count(&Point::new(0, 0), &pair), 1);
+        assert_eq!(grid.neighbor_count(&Point::new(0, 1), &pair), 1);
+    }
+
+    #[test]
+    fn test_accessibility_rules() {
+        let corners = parse_input("@.@\n...\n@.@");
+        let flat = Accessibility {
+            neighbourhood: Neighbourhood::moore(1),
+            threshold: 3,
+        };
+        let torus = Accessibility {
+            neighbourhood: Neighbourhood::moore(1).wrapping(3, 3),
+            threshold: 3,
+        };
+        assert_eq!(corners.accessible_count(&flat), 4);
+        assert_eq!(corners.accessible_count(&torus), 0);
+
+        // Only the cell to the right counts, so a row erodes from its right
+        // end one roll per round.
+        let right = Accessibility {
+            neighbourhood: Neighbourhood::custom([Point::new(0, 1)]),
+            threshold: 1,
+        };
+        let rounds = parse_input("@@@@").erode(&right);
+        assert_eq!(rounds.len(), 4);
+        assert!(rounds.iter().all(|r| r.removed == 1));
+    }
+
+    #[test]
+    fn test_try_parse_edits() {
+        assert_eq!(
+            try_parse_edits("+1,2\n\n-0, 3\n"),
+            Ok(vec![
+                Edit::Add(Point::new(1, 2)),
+                Edit::Remove(Point::new(0, 3))
+            ])
+        );
+        assert_eq!(
+            try_parse_edits("+1,2\n*1,2"),
+            Err(EditError {
+                line: 2,
+                text: "*1,2".to_string()
+            })
+        );
+        assert!(try_parse_edits("+1").is_err());
+        assert!(try_parse_edits("-a,2").is_err());
+    }
+
+    #[test]
+    fn test_live_grid_matches_recompute() {
+        let mut grid = parse_input(EXAMPLE);
+        let rule = Accessibility::puzzle();
+        let mut live = LiveGrid::new(&grid, rule.clone());
+        assert_eq!(live.accessible_count(), 13);
+
+        let edits = try_parse_edits(
+            "+0,0\n-1,1\n-1,1\n+1,1\n+0,4\n-5,5\n+10,10\n+0,3,1\n+1,3,1\n+1,3,-1\n-0,3,1",
+        )
+        .unwrap();
+        let counts = live.replay(&edits);
+
+        let expected: Vec<usize> = edits
+            .iter()
+            .map(|edit| {
+                match edit {
+                    Edit::Add(p) => grid.rolls.insert(p.clone()),
+                    Edit::Remove(p) => grid.rolls.remove(p),
+                };
+                grid.accessible_count(&rule)
+            })
+            .collect();
+
+        assert_eq!(counts, expected);
+        assert_eq!(live.roll_count(), grid.rolls.len());
+        assert!(live.is_accessible(&Point::new(10, 10)));
+        assert!(!live.insert(Point::new(10, 10)));
+        // Resting on the packed rolls below
+        assert!(!live.is_accessible(&Point::new_3d(1, 3, 1)));
+        assert_eq!(edits[6].to_string(), "+10,10");
+        assert_eq!(edits[10].to_string(), "-0,3,1");
+        assert_eq!(edits[9].to_string(), "+1,3,-1");
+    }
+
+    #[test]
+    fn test_stacked_layers() {
+        // A single roll stacked above cell (0, 3) has four neighbours below,
+        // and becomes a fourth neighbour of two accessible rolls.
+        let stacked = format!("{}\n\n...@\n\n", EXAMPLE);
+        let grid = parse_input(&stacked);
+
+        assert_eq!(grid.rolls.len(), 72);
+        assert!(grid.has_roll_at(&Point::new_3d(0, 3, 1)));
+        assert_eq!(part1(&grid), 11);
+        assert_eq!(part1(&parse_input(&format!("\n{}\n", EXAMPLE))), 13);
+
+        let layers = grid.layers(&Accessibility::puzzle());
+        let rendered = layers.render();
+        let top = rendered.split("\n\n").nth(1).unwrap();
+        assert_eq!(rendered.split("\n\n").count(), 2);
+        assert_eq!(top.lines().count(), 10);
+        assert!(top.starts_with("...") && !top.starts_with("...."));
+
+        let cube = "@@@\n@@@\n@@@\n\n@@@\n@@@\n@@@\n\n@@@\n@@@\n@@@";
+        assert_eq!(part1(&parse_input(cube)), 0);
+        assert_eq!(part2(&parse_input(cube)), 0);
+
+        let map = wide_map(70, 20).replacen("\n", "\n\n", 3);
+        assert_eq!(part1_bits(&BitGrid::parse(&map)), part1(&parse_input(&map)));
+        assert_eq!(part2_bits(&BitGrid::parse(&map)), part2(&parse_input(&map)));
     }
 }